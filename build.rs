@@ -13,7 +13,7 @@ fn main() {
         if !webui_dir.join("node_modules").exists() {
             println!("cargo:info=Installing bun dependencies for webui...");
             let install_status = if cfg!(target_os = "windows") {
-                Command::new("cmd").args(&["/C", "cd webui && bun install"]).status()
+                Command::new("cmd").args(["/C", "cd webui && bun install"]).status()
             } else {
                 Command::new("sh").arg("-c").arg("cd webui && bun install").status()
             };
@@ -24,7 +24,7 @@ fn main() {
         }
 
         let build_status = if cfg!(target_os = "windows") {
-            Command::new("cmd").args(&["/C", "cd webui && bun run build"]).status()
+            Command::new("cmd").args(["/C", "cd webui && bun run build"]).status()
         } else {
             Command::new("sh").arg("-c").arg("cd webui && bun run build").status()
         };
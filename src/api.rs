@@ -1,14 +1,47 @@
-use std::{ collections::HashMap, path::PathBuf, sync::Arc };
+use std::{ collections::{ BTreeMap, HashMap }, mem, path::PathBuf, sync::Arc };
 use axum::{
-    extract::{ Path as AxPath, State },
-    http::StatusCode,
-    response::{ IntoResponse, Json },
+    extract::{ Path as AxPath, Query, State },
+    http::{ header, HeaderMap, StatusCode },
+    response::{ IntoResponse, Json, Response },
 };
-use serde::{ Deserialize, Serialize };
-
-use crate::models::{ AppConfig, AppState, Episode, PlaylistItem, SubtitleMode };
-use crate::streaming::{ play_file, start_tv_loop_if_needed, stop_streaming };
-use crate::video::{ organize_shows_and_episodes, scan_for_videos };
+use serde::{ Deserialize, Deserializer, Serialize };
+
+use crate::config::{ save_config, save_cursors };
+use crate::cursors::{ aired_files, next_position, set_next_position, update_cursors, TV_CHANNEL };
+use crate::health::{ health_report, start_health_check, HealthCheckOptions, HealthJobStatus, HealthReport };
+use crate::library::{ new_library_root, root_for_path };
+use crate::models::{
+    AppState,
+    ChannelCommand,
+    ChannelRotation,
+    Episode,
+    EpisodeOverride,
+    EpisodeRange,
+    EpisodeRef,
+    LibraryKind,
+    LibraryRoot,
+    MediaMetadata,
+    Overrides,
+    NowPlaying,
+    PlayMode,
+    PlayOrder,
+    PlaylistEndPolicy,
+    PlaylistItem,
+    ScanSettings,
+    ScheduledEpisode,
+    ShowCursor,
+    ShowNaming,
+    SpecialKind,
+    SubtitleMode,
+};
+use crate::order::random_seed;
+use crate::parser::ParseRules;
+use crate::scan::{ start_scan_job, ScanJobState, ScanJobStatus };
+use crate::search::{ self, SearchPage, SearchQuery };
+use crate::stats::{ library_stats, LibraryStats };
+use crate::streaming::{ enqueue, start_tv_loop_if_needed, stop_streaming };
+use crate::video::{ available_files, fnv1a, parse_episode_info, playlist_episodes, relabel_library };
+use crate::watcher::restart_library_watcher;
 
 #[derive(Serialize)]
 pub struct ApiResponse<T> {
@@ -38,22 +71,21 @@ impl<T> ApiResponse<T> {
 #[derive(Serialize)]
 pub struct ConfigResponse {
     pub videos_folder: Option<String>,
+    pub libraries: Vec<LibraryRoot>,
     pub video_count: usize,
     pub show_count: usize,
     pub shows: HashMap<String, Vec<Episode>>,
+    pub show_metadata: HashMap<String, MediaMetadata>,
     pub playlist: Vec<PlaylistItem>,
+    pub active_playlist: String,
     pub subtitle_mode: SubtitleMode,
+    pub playlist_end: PlaylistEndPolicy,
+    pub channel_rotation: ChannelRotation,
+    pub watch_library: bool,
     pub is_streaming: bool,
     pub current_playing: Option<String>,
 }
 
-#[derive(Serialize)]
-pub struct ScanResponse {
-    pub video_count: usize,
-    pub show_count: usize,
-    pub shows: HashMap<String, Vec<Episode>>,
-}
-
 #[derive(Serialize)]
 pub struct FileListResponse {
     pub files: Vec<FileInfo>,
@@ -69,6 +101,28 @@ pub struct FileInfo {
 #[derive(Serialize)]
 pub struct ShowListResponse {
     pub shows: Vec<String>,
+    pub metadata: HashMap<String, MediaMetadata>,
+}
+
+#[derive(Deserialize)]
+pub struct ParsePreviewQuery {
+    pub path: String,
+}
+
+#[derive(Serialize)]
+pub struct ParsePreviewResponse {
+    pub path: String,
+    /// Library the path belongs to, if any
+    pub library_id: Option<String>,
+    /// Index of the matching rule in `parse_rules`; `None` means the built-in heuristics were used
+    pub rule: Option<usize>,
+    pub show_name: String,
+    pub season: Option<usize>,
+    pub episode_number: Option<usize>,
+    pub episode_end: Option<usize>,
+    pub sub_episode: Option<u32>,
+    pub special: Option<SpecialKind>,
+    pub title: Option<String>,
 }
 
 #[derive(Deserialize)]
@@ -76,21 +130,152 @@ pub struct SetFolderRequest {
     pub path: String,
 }
 
+/// Body for adding or updating a library root; omitted fields keep their value
+#[derive(Deserialize)]
+pub struct LibraryRequest {
+    pub path: Option<String>,
+    pub kind: Option<LibraryKind>,
+    pub ignore: Option<Vec<String>>,
+    pub naming: Option<ShowNaming>,
+    pub enabled: Option<bool>,
+}
+
+impl LibraryRequest {
+    fn apply(&self, root: &mut LibraryRoot) {
+        if let Some(kind) = self.kind {
+            root.kind = kind;
+        }
+        if let Some(ignore) = &self.ignore {
+            root.ignore = ignore.clone();
+        }
+        if let Some(naming) = self.naming {
+            root.naming = naming;
+        }
+        if let Some(enabled) = self.enabled {
+            root.enabled = enabled;
+        }
+    }
+}
+
 #[derive(Deserialize)]
 pub struct PlayFileRequest {
     pub file_path: String,
 }
 
+#[derive(Deserialize)]
+pub struct QueueRequest {
+    /// A library episode, or else `file_path`
+    pub file_id: Option<String>,
+    pub file_path: Option<String>,
+    /// Interrupt the current episode instead of waiting for it to end
+    #[serde(default)]
+    pub now: bool,
+}
+
 #[derive(Deserialize)]
 pub struct SetSubtitleModeRequest {
     pub mode: SubtitleMode,
 }
 
+#[derive(Deserialize)]
+pub struct RenameShowRequest {
+    pub name: String,
+}
+
+#[derive(Deserialize)]
+pub struct SetWatchLibraryRequest {
+    pub enabled: bool,
+}
+
 #[derive(Deserialize)]
 pub struct AddToPlaylistRequest {
-    pub show_name: String,
-    pub episode_range: Option<(usize, usize)>,
+    /// One of `show_name`, `collection` or `genre` (the last two may be combined)
+    pub show_name: Option<String>,
+    pub collection: Option<String>,
+    pub genre: Option<String>,
+    /// Inclusive, by episode number, e.g. "S1E5-S2E3"
+    pub range: Option<EpisodeRange>,
+    #[serde(default)]
+    pub episodes: Vec<EpisodeRef>,
+    #[serde(default)]
+    pub exclude: Vec<EpisodeRef>,
     pub repeat_count: Option<usize>,
+    /// Defaults to `Once`, or `Repeat` when `repeat_count` is above zero
+    pub mode: Option<PlayMode>,
+    pub order: Option<PlayOrder>,
+    /// Seed for `Shuffle`; a random one is picked if omitted
+    pub seed: Option<u64>,
+    pub weight: Option<u32>,
+}
+
+#[derive(Deserialize)]
+pub struct PlaylistNameRequest {
+    pub name: String,
+}
+
+#[derive(Deserialize)]
+pub struct CreatePlaylistRequest {
+    pub name: String,
+    /// Starts empty if omitted
+    #[serde(default)]
+    pub items: Vec<PlaylistItem>,
+}
+
+#[derive(Deserialize)]
+pub struct ActivatePlaylistRequest {
+    pub name: String,
+    /// Switch right away instead of after the current episode
+    #[serde(default)]
+    pub immediate: bool,
+}
+
+#[derive(Serialize)]
+pub struct NamedPlaylist {
+    pub name: String,
+    pub active: bool,
+    pub items: Vec<PlaylistItem>,
+}
+
+#[derive(Deserialize)]
+pub struct SetPlaylistEndPolicyRequest {
+    pub policy: PlaylistEndPolicy,
+}
+
+#[derive(Deserialize)]
+pub struct SetChannelRotationRequest {
+    pub rotation: ChannelRotation,
+}
+
+#[derive(Deserialize)]
+pub struct SeekQuery {
+    /// `ss`, `mm:ss` or `hh:mm:ss`
+    pub to: String,
+}
+
+#[derive(Deserialize)]
+pub struct JumpCursorRequest {
+    /// Episode to air next
+    pub file_id: Option<String>,
+    /// Index of the episode to air next, in show order
+    pub position: Option<usize>,
+}
+
+#[derive(Deserialize)]
+pub struct RewindCursorRequest {
+    /// Episodes to step back; defaults to 1
+    pub count: Option<usize>,
+}
+
+#[derive(Serialize)]
+pub struct CursorResponse {
+    pub show_name: String,
+    /// `None` when the show starts from its first episode
+    pub cursor: Option<ShowCursor>,
+    /// Index of the episode that airs next
+    pub next_position: usize,
+    /// `None` once every episode has aired
+    pub next_file_id: Option<String>,
+    pub next_episode: Option<String>,
 }
 
 #[derive(Deserialize)]
@@ -99,15 +284,68 @@ pub struct MovePlaylistItemRequest {
     pub direction: String,
 }
 
+/// `{index}` of `/api/playlist/{index}`, or `{name}` and `{index}` of
+/// `/api/playlists/{name}/items/{index}`
+#[derive(Deserialize)]
+pub struct PlaylistItemPath {
+    pub name: Option<String>,
+    pub index: usize,
+}
+
+#[derive(Deserialize)]
+pub struct MovePlaylistItemToRequest {
+    pub to: usize,
+}
+
+/// Fields left out stay as they are
+#[derive(Deserialize)]
+pub struct UpdatePlaylistItemRequest {
+    /// `null` removes the range
+    #[serde(default, deserialize_with = "present")]
+    pub range: Option<Option<EpisodeRange>>,
+    /// An empty list removes the listed episodes
+    pub episodes: Option<Vec<EpisodeRef>>,
+    pub exclude: Option<Vec<EpisodeRef>>,
+    pub repeat_count: Option<usize>,
+    /// `null` derives the mode from `repeat_count` again
+    #[serde(default, deserialize_with = "present")]
+    pub mode: Option<Option<PlayMode>>,
+    pub order: Option<PlayOrder>,
+    pub seed: Option<u64>,
+    /// `null` resets the weight to 1
+    #[serde(default, deserialize_with = "present")]
+    pub weight: Option<Option<u32>>,
+}
+
+/// Tells an explicit `null` (`Some(None)`) apart from a missing field (`None`)
+fn present<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+    where D: Deserializer<'de>, T: Deserialize<'de>
+{
+    T::deserialize(deserializer).map(Some)
+}
+
+#[derive(Deserialize)]
+pub struct HealthCheckQuery {
+    pub library: Option<String>,
+    /// Defaults to `scan_settings.verify_decode`
+    pub decode: Option<bool>,
+    /// Re-check files whose last result is still current
+    #[serde(default)]
+    pub full: bool,
+}
+
 /// GET /api/config
 pub async fn get_config(State(state): State<Arc<AppState>>) -> impl IntoResponse {
-    let videos_folder = state.videos_folder
-        .read().await
-        .as_ref()
-        .map(|p| p.display().to_string());
+    let libraries = state.libraries.read().await.clone();
+    let videos_folder = libraries.first().map(|root| root.path.display().to_string());
     let shows = state.shows.read().await.clone();
+    let show_metadata = state.show_metadata.read().await.clone();
     let playlist = state.playlist.read().await.clone();
+    let active_playlist = state.active_playlist.read().await.clone();
     let subtitle_mode = state.subtitle_mode.read().await.clone();
+    let playlist_end = *state.playlist_end.read().await;
+    let channel_rotation = *state.channel_rotation.read().await;
+    let watch_library = *state.watch_library.read().await;
     let is_streaming = *state.is_playing.read().await;
     let current_playing = state.current_playing
         .read().await
@@ -122,11 +360,17 @@ pub async fn get_config(State(state): State<Arc<AppState>>) -> impl IntoResponse
 
     let response = ConfigResponse {
         videos_folder,
+        libraries,
         video_count,
         show_count,
         shows,
+        show_metadata,
         playlist,
+        active_playlist,
         subtitle_mode,
+        playlist_end,
+        channel_rotation,
+        watch_library,
         is_streaming,
         current_playing,
     };
@@ -148,9 +392,22 @@ pub async fn set_folder(
         );
     }
 
-    *state.videos_folder.write().await = Some(path.clone());
+    // Kept for single-folder clients: points the primary library root at `path`
+    {
+        let mut libraries = state.libraries.write().await;
+        match libraries.first_mut() {
+            Some(root) => {
+                root.path = path;
+            }
+            None => {
+                let root = new_library_root(&path, &libraries);
+                libraries.push(root);
+            }
+        }
+    }
+    restart_library_watcher(state.clone()).await;
 
-    if let Err(e) = save_config_internal(state).await {
+    if let Err(e) = save_config(&state).await {
         return (
             StatusCode::INTERNAL_SERVER_ERROR,
             Json(ApiResponse::<()>::error(format!("Failed to save config: {}", e))),
@@ -160,268 +417,1129 @@ pub async fn set_folder(
     (StatusCode::OK, Json(ApiResponse::success(())))
 }
 
-/// POST /api/scan
-pub async fn scan_videos(State(state): State<Arc<AppState>>) -> impl IntoResponse {
-    let videos_folder = state.videos_folder.read().await.clone();
+/// GET /api/libraries
+pub async fn get_libraries(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    let libraries = state.libraries.read().await.clone();
+    Json(ApiResponse::success(libraries))
+}
+
+/// POST /api/libraries
+pub async fn add_library(
+    State(state): State<Arc<AppState>>,
+    Json(req): Json<LibraryRequest>
+) -> impl IntoResponse {
+    let Some(path) = req.path.clone().map(PathBuf::from) else {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(ApiResponse::<LibraryRoot>::error("Missing library path".to_string())),
+        );
+    };
 
-    if videos_folder.is_none() {
+    if !path.is_dir() {
         return (
             StatusCode::BAD_REQUEST,
-            Json(ApiResponse::<ScanResponse>::error("No videos folder set".to_string())),
+            Json(ApiResponse::<LibraryRoot>::error("Folder does not exist".to_string())),
         );
     }
 
-    let folder = videos_folder.unwrap();
-    let video_files = scan_for_videos(&folder).await;
-    let video_count = video_files.len();
-
-    let shows = organize_shows_and_episodes(&video_files).await;
-    let show_count = shows.len();
+    let root = {
+        let mut libraries = state.libraries.write().await;
+        if libraries.iter().any(|root| root.path == path) {
+            return (
+                StatusCode::CONFLICT,
+                Json(ApiResponse::<LibraryRoot>::error("Folder is already a library".to_string())),
+            );
+        }
 
-    *state.shows.write().await = shows.clone();
+        let mut root = new_library_root(&path, &libraries);
+        req.apply(&mut root);
+        libraries.push(root.clone());
+        root
+    };
 
-    let mut tv_files = Vec::new();
-    for episodes in shows.values() {
-        for episode in episodes {
-            tv_files.push(episode.file_path.clone());
-        }
-    }
-    *state.tv_files.write().await = tv_files;
+    restart_library_watcher(state.clone()).await;
 
-    if let Err(e) = save_config_internal(state).await {
+    if let Err(e) = save_config(&state).await {
         return (
             StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ApiResponse::<ScanResponse>::error(format!("Failed to save config: {}", e))),
+            Json(ApiResponse::<LibraryRoot>::error(format!("Failed to save config: {}", e))),
         );
     }
 
-    let response = ScanResponse {
-        video_count,
-        show_count,
-        shows,
-    };
-
-    (StatusCode::OK, Json(ApiResponse::success(response)))
+    (StatusCode::OK, Json(ApiResponse::success(root)))
 }
 
-/// GET /api/files
-pub async fn get_files(State(state): State<Arc<AppState>>) -> impl IntoResponse {
-    let shows = state.shows.read().await.clone();
-    let mut files = Vec::new();
+/// PUT /api/libraries/{id}
+pub async fn update_library(
+    State(state): State<Arc<AppState>>,
+    AxPath(id): AxPath<String>,
+    Json(req): Json<LibraryRequest>
+) -> impl IntoResponse {
+    if let Some(path) = &req.path && !PathBuf::from(path).is_dir() {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(ApiResponse::<LibraryRoot>::error("Folder does not exist".to_string())),
+        );
+    }
 
-    for (show_name, episodes) in &shows {
-        for episode in episodes {
-            files.push(FileInfo {
-                display_name: format!("{} - {}", show_name, episode.name),
-                file_path: episode.file_path.display().to_string(),
-                show_name: show_name.clone(),
-            });
+    let root = {
+        let mut libraries = state.libraries.write().await;
+        let Some(root) = libraries.iter_mut().find(|root| root.id == id) else {
+            return (
+                StatusCode::NOT_FOUND,
+                Json(ApiResponse::<LibraryRoot>::error("Library not found".to_string())),
+            );
+        };
+
+        if let Some(path) = &req.path {
+            root.path = PathBuf::from(path);
         }
-    }
+        req.apply(root);
+        root.clone()
+    };
 
-    let response = FileListResponse { files };
-    Json(ApiResponse::success(response))
-}
+    restart_library_watcher(state.clone()).await;
 
-/// GET /api/shows
-pub async fn get_shows(State(state): State<Arc<AppState>>) -> impl IntoResponse {
-    let shows = state.shows.read().await;
-    let mut show_names: Vec<String> = shows.keys().cloned().collect();
-    show_names.sort();
+    if let Err(e) = save_config(&state).await {
+        return (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ApiResponse::<LibraryRoot>::error(format!("Failed to save config: {}", e))),
+        );
+    }
 
-    let response = ShowListResponse { shows: show_names };
-    Json(ApiResponse::success(response))
+    (StatusCode::OK, Json(ApiResponse::success(root)))
 }
 
-/// POST /api/play
-pub async fn play_video(
+/// DELETE /api/libraries/{id}
+pub async fn remove_library(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<PlayFileRequest>
+    AxPath(id): AxPath<String>
 ) -> impl IntoResponse {
-    let file_path = PathBuf::from(&req.file_path);
+    {
+        let mut libraries = state.libraries.write().await;
+        let Some(index) = libraries.iter().position(|root| root.id == id) else {
+            return (
+                StatusCode::NOT_FOUND,
+                Json(ApiResponse::<()>::error("Library not found".to_string())),
+            );
+        };
+        libraries.remove(index);
+    }
 
-    if !file_path.exists() {
-        return (
-            StatusCode::BAD_REQUEST,
-            Json(ApiResponse::<()>::error("File does not exist".to_string())),
-        );
+    {
+        let mut shows = state.shows.write().await;
+        for episodes in shows.values_mut() {
+            episodes.retain(|episode| episode.library_id != id);
+        }
+        shows.retain(|_, episodes| !episodes.is_empty());
+        *state.tv_files.write().await = available_files(&shows);
+        *state.library_stats.write().await = None;
     }
 
-    if let Err(e) = play_file(state, file_path).await {
+    restart_library_watcher(state.clone()).await;
+
+    if let Err(e) = save_config(&state).await {
         return (
             StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ApiResponse::<()>::error(format!("Failed to play file: {}", e))),
+            Json(ApiResponse::<()>::error(format!("Failed to save config: {}", e))),
         );
     }
 
     (StatusCode::OK, Json(ApiResponse::success(())))
 }
 
-/// POST /api/stop
-pub async fn stop_playback(State(state): State<Arc<AppState>>) -> impl IntoResponse {
-    stop_streaming(state).await;
-    Json(ApiResponse::success(()))
+/// POST /api/libraries/{id}/scan
+pub async fn scan_library(
+    State(state): State<Arc<AppState>>,
+    AxPath(id): AxPath<String>
+) -> impl IntoResponse {
+    match start_scan_job(state, Some(&id)).await {
+        Ok(job) => (StatusCode::ACCEPTED, Json(ApiResponse::success(job.status()))),
+        Err((status, e)) => (status, Json(ApiResponse::<ScanJobStatus>::error(e))),
+    }
 }
 
-/// POST /api/start-streaming
-pub async fn start_streaming(State(state): State<Arc<AppState>>) -> impl IntoResponse {
-    let shows = state.shows.read().await;
-    if shows.is_empty() {
-        return (
-            StatusCode::BAD_REQUEST,
-            Json(ApiResponse::<()>::error("No videos available. Please scan first.".to_string())),
-        );
+/// POST /api/scan
+pub async fn scan_videos(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    match start_scan_job(state, None).await {
+        Ok(job) => (StatusCode::ACCEPTED, Json(ApiResponse::success(job.status()))),
+        Err((status, e)) => (status, Json(ApiResponse::<ScanJobStatus>::error(e))),
     }
-    drop(shows);
+}
 
-    start_tv_loop_if_needed(state).await;
-    (StatusCode::OK, Json(ApiResponse::success(())))
+/// GET /api/scan/{job}
+pub async fn get_scan_job(
+    State(state): State<Arc<AppState>>,
+    AxPath(job_id): AxPath<String>
+) -> impl IntoResponse {
+    match state.scan_jobs.read().await.get(&job_id) {
+        Some(job) => (StatusCode::OK, Json(ApiResponse::success(job.status()))),
+        None =>
+            (
+                StatusCode::NOT_FOUND,
+                Json(ApiResponse::<ScanJobStatus>::error("Scan job not found".to_string())),
+            ),
+    }
 }
 
-/// POST /api/subtitle-mode
-pub async fn set_subtitle_mode(
+/// POST /api/scan/{job}/cancel
+pub async fn cancel_scan_job(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<SetSubtitleModeRequest>
+    AxPath(job_id): AxPath<String>
 ) -> impl IntoResponse {
-    *state.subtitle_mode.write().await = req.mode.clone();
+    let jobs = state.scan_jobs.read().await;
+    let Some(job) = jobs.get(&job_id) else {
+        return (
+            StatusCode::NOT_FOUND,
+            Json(ApiResponse::<ScanJobStatus>::error("Scan job not found".to_string())),
+        );
+    };
 
-    // Save config
-    if let Err(e) = save_config_internal(state).await {
+    if job.state() != ScanJobState::Running {
         return (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ApiResponse::<()>::error(format!("Failed to save config: {}", e))),
+            StatusCode::CONFLICT,
+            Json(ApiResponse::<ScanJobStatus>::error("Scan job is not running".to_string())),
         );
     }
 
-    (StatusCode::OK, Json(ApiResponse::success(())))
+    job.cancel();
+    (StatusCode::OK, Json(ApiResponse::success(job.status())))
 }
 
-// Playlist Management Handlers
+/// GET /api/library/health
+pub async fn get_library_health(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    Json(ApiResponse::<HealthReport>::success(health_report(&state).await))
+}
 
-/// GET /api/playlist
-pub async fn get_playlist(State(state): State<Arc<AppState>>) -> impl IntoResponse {
-    let playlist = state.playlist.read().await.clone();
-    Json(ApiResponse::success(playlist))
+/// POST /api/library/health/check?library=...&decode=true&full=true
+pub async fn check_library_health(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<HealthCheckQuery>
+) -> impl IntoResponse {
+    let decode = match query.decode {
+        Some(decode) => decode,
+        None => state.scan_settings.read().await.verify_decode,
+    };
+    let options = HealthCheckOptions {
+        library: query.library,
+        decode,
+        full: query.full,
+        max_age: None,
+    };
+
+    match start_health_check(state, options).await {
+        Ok(job) => (StatusCode::ACCEPTED, Json(ApiResponse::success(job.status()))),
+        Err((status, e)) => (status, Json(ApiResponse::<HealthJobStatus>::error(e))),
+    }
 }
 
-/// POST /api/playlist/add
-pub async fn add_to_playlist(
+/// POST /api/library/health/cancel
+pub async fn cancel_health_check(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    let job = state.health_job.read().await.clone();
+    match job {
+        Some(job) if job.state() == ScanJobState::Running => {
+            job.cancel();
+            (StatusCode::OK, Json(ApiResponse::success(job.status())))
+        }
+        _ =>
+            (
+                StatusCode::CONFLICT,
+                Json(ApiResponse::<HealthJobStatus>::error("No integrity check is running".to_string())),
+            ),
+    }
+}
+
+/// DELETE /api/library/health/{file_id} - forget a result, e.g. after replacing
+/// a broken file with one of the same name and size
+pub async fn clear_file_health(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<AddToPlaylistRequest>
+    AxPath(file_id): AxPath<String>
 ) -> impl IntoResponse {
-    let shows = state.shows.read().await;
-    if !shows.contains_key(&req.show_name) {
+    if state.file_health.write().await.remove(&file_id).is_none() {
         return (
-            StatusCode::BAD_REQUEST,
-            Json(ApiResponse::<()>::error("Show not found".to_string())),
+            StatusCode::NOT_FOUND,
+            Json(ApiResponse::<String>::error("No integrity check result for that file".to_string())),
         );
     }
-    drop(shows);
-
-    let item = PlaylistItem {
-        show_name: req.show_name,
-        episode_range: req.episode_range,
-        repeat_count: req.repeat_count.unwrap_or(0),
-    };
-
-    state.playlist.write().await.push(item);
 
-    if let Err(e) = save_config_internal(state).await {
+    if let Err(e) = save_config(&state).await {
         return (
             StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ApiResponse::<()>::error(format!("Failed to save config: {}", e))),
+            Json(ApiResponse::<String>::error(format!("Failed to save config: {}", e))),
         );
     }
 
-    (StatusCode::OK, Json(ApiResponse::success(())))
+    (StatusCode::OK, Json(ApiResponse::success("Integrity check result cleared".to_string())))
 }
 
-/// DELETE /api/playlist/{index}
-pub async fn remove_from_playlist(
+/// GET /api/scan/settings
+pub async fn get_scan_settings(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    let settings = state.scan_settings.read().await.clone();
+    Json(ApiResponse::success(settings))
+}
+
+/// PUT /api/scan/settings - applies to the next scan
+pub async fn set_scan_settings(
     State(state): State<Arc<AppState>>,
-    AxPath(index): AxPath<usize>
+    Json(mut req): Json<ScanSettings>
 ) -> impl IntoResponse {
-    let mut playlist = state.playlist.write().await;
+    // Accept ".MKV" as well as "mkv"
+    for extension in req.video_extensions.iter_mut().chain(req.audio_extensions.iter_mut()) {
+        *extension = extension.trim().trim_start_matches('.').to_lowercase();
+    }
+    req.video_extensions.retain(|ext| !ext.is_empty());
+    req.audio_extensions.retain(|ext| !ext.is_empty());
+    req.probe_workers = req.probe_workers.max(1);
 
-    if index >= playlist.len() {
+    if req.video_extensions.is_empty() || req.audio_extensions.is_empty() {
         return (
             StatusCode::BAD_REQUEST,
-            Json(ApiResponse::<()>::error("Invalid playlist index".to_string())),
+            Json(ApiResponse::<ScanSettings>::error("Extension lists must not be empty".to_string())),
         );
     }
 
-    playlist.remove(index);
-    drop(playlist);
+    *state.scan_settings.write().await = req.clone();
 
-    if let Err(e) = save_config_internal(state).await {
+    if let Err(e) = save_config(&state).await {
         return (
             StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ApiResponse::<()>::error(format!("Failed to save config: {}", e))),
+            Json(ApiResponse::<ScanSettings>::error(format!("Failed to save config: {}", e))),
         );
     }
 
-    (StatusCode::OK, Json(ApiResponse::success(())))
+    (StatusCode::OK, Json(ApiResponse::success(req)))
 }
 
-/// POST /api/playlist/move
-pub async fn move_playlist_item(
+/// GET /api/scan/preview?path=...
+pub async fn preview_parse(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<MovePlaylistItemRequest>
+    Query(query): Query<ParsePreviewQuery>
 ) -> impl IntoResponse {
-    let mut playlist = state.playlist.write().await;
-
-    if req.index >= playlist.len() {
+    let path = PathBuf::from(&query.path);
+    if path.file_name().is_none() {
         return (
             StatusCode::BAD_REQUEST,
-            Json(ApiResponse::<()>::error("Invalid playlist index".to_string())),
+            Json(ApiResponse::<ParsePreviewResponse>::error("Path has no file name".to_string())),
         );
     }
 
-    match req.direction.as_str() {
-        "up" => {
-            if req.index == 0 {
-                return (
-                    StatusCode::BAD_REQUEST,
-                    Json(ApiResponse::<()>::error("Cannot move first item up".to_string())),
-                );
-            }
-            playlist.swap(req.index - 1, req.index);
-        }
-        "down" => {
-            if req.index >= playlist.len() - 1 {
-                return (
-                    StatusCode::BAD_REQUEST,
-                    Json(ApiResponse::<()>::error("Cannot move last item down".to_string())),
-                );
-            }
-            playlist.swap(req.index, req.index + 1);
-        }
-        _ => {
-            return (
-                StatusCode::BAD_REQUEST,
-                Json(ApiResponse::<()>::error("Invalid direction. Use 'up' or 'down'".to_string())),
-            );
-        }
-    }
+    // Paths outside every library are parsed with default settings relative to their folder
+    let libraries = state.libraries.read().await;
+    let root = root_for_path(&libraries, &path).cloned();
+    let library_id = root.as_ref().map(|root| root.id.clone());
+    let root = root.unwrap_or_else(|| {
+        let mut root = new_library_root(path.parent().unwrap_or(&path), &[]);
+        root.id = String::new();
+        root
+    });
+    drop(libraries);
+
+    let rules = ParseRules::new(&state.parse_rules.read().await);
+    let (episode, rule) = parse_episode_info(&path, &root, &rules);
+
+    let response = ParsePreviewResponse {
+        path: query.path,
+        library_id,
+        rule,
+        show_name: episode.show_name,
+        season: episode.season,
+        episode_number: episode.episode_number,
+        episode_end: episode.episode_end,
+        sub_episode: episode.sub_episode,
+        special: episode.special,
+        title: episode.title,
+    };
 
-    drop(playlist);
+    (StatusCode::OK, Json(ApiResponse::success(response)))
+}
 
-    if let Err(e) = save_config_internal(state).await {
-        return (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ApiResponse::<()>::error(format!("Failed to save config: {}", e))),
-        );
-    }
+/// GET /api/files
+pub async fn get_files(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    let shows = state.shows.read().await.clone();
+    let mut files = Vec::new();
+
+    for (show_name, episodes) in &shows {
+        for episode in episodes {
+            files.push(FileInfo {
+                display_name: format!("{} - {}", show_name, episode.name),
+                file_path: episode.file_path.display().to_string(),
+                show_name: show_name.clone(),
+            });
+        }
+    }
+
+    let response = FileListResponse { files };
+    Json(ApiResponse::success(response))
+}
+
+/// GET /api/library/search?q=...&show=...&season=...&genre=...&watched=...&sort=...&cursor=...
+pub async fn search_library(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<SearchQuery>
+) -> impl IntoResponse {
+    let shows = state.shows.read().await;
+    let show_metadata = state.show_metadata.read().await;
+    let aired = aired_files(state.cursors.read().await.get(TV_CHANNEL), &shows);
+
+    match search::search_library(&shows, &show_metadata, &aired, &query) {
+        Ok(page) => (StatusCode::OK, Json(ApiResponse::success(page))),
+        Err(e) => (StatusCode::BAD_REQUEST, Json(ApiResponse::<SearchPage>::error(e))),
+    }
+}
+
+/// GET /api/library/stats
+pub async fn get_library_stats(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    let stats = library_stats(&state).await;
+    Json(ApiResponse::<Arc<LibraryStats>>::success(stats))
+}
+
+/// GET /api/shows
+pub async fn get_shows(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    let shows = state.shows.read().await;
+    let mut show_names: Vec<String> = shows.keys().cloned().collect();
+    show_names.sort();
+
+    let metadata = state.show_metadata.read().await.clone();
+    let response = ShowListResponse { shows: show_names, metadata };
+    Json(ApiResponse::success(response))
+}
+
+/// POST /api/play
+pub async fn play_video(
+    State(state): State<Arc<AppState>>,
+    Json(req): Json<PlayFileRequest>
+) -> impl IntoResponse {
+    let entry = match queue_entry(&state, None, Some(&req.file_path)).await {
+        Ok(entry) => entry,
+        Err(e) => {
+            return (StatusCode::BAD_REQUEST, Json(ApiResponse::<()>::error(e)));
+        }
+    };
+
+    // Plays right away; the schedule continues afterwards
+    enqueue(state, entry, true).await;
+    (StatusCode::OK, Json(ApiResponse::success(())))
+}
+
+/// Queue entry for a library episode, or for any existing file
+async fn queue_entry(
+    state: &AppState,
+    file_id: Option<&str>,
+    file_path: Option<&str>
+) -> Result<ScheduledEpisode, String> {
+    let shows = state.shows.read().await;
+    let mut episodes = shows.values().flatten();
+
+    if let Some(file_id) = file_id {
+        return episodes
+            .find(|episode| episode.file_id == file_id)
+            .map(ScheduledEpisode::of)
+            .ok_or_else(|| format!("Episode not found: {}", file_id));
+    }
+
+    let Some(file_path) = file_path.map(PathBuf::from) else {
+        return Err("Either file_id or file_path is required".to_string());
+    };
+    if !file_path.exists() {
+        return Err("File does not exist".to_string());
+    }
+
+    Ok(match episodes.find(|episode| episode.file_path == file_path) {
+        Some(episode) => ScheduledEpisode::of(episode),
+        None =>
+            ScheduledEpisode {
+                show_name: String::new(),
+                name: file_path
+                    .file_stem()
+                    .map(|stem| stem.to_string_lossy().into_owned())
+                    .unwrap_or_default(),
+                file_path,
+            },
+    })
+}
+
+/// GET /api/queue
+pub async fn get_queue(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    let queue: Vec<ScheduledEpisode> = state.play_queue.read().await.iter().cloned().collect();
+    Json(ApiResponse::success(queue))
+}
+
+/// POST /api/queue
+pub async fn add_to_queue(
+    State(state): State<Arc<AppState>>,
+    Json(req): Json<QueueRequest>
+) -> impl IntoResponse {
+    let entry = match queue_entry(&state, req.file_id.as_deref(), req.file_path.as_deref()).await {
+        Ok(entry) => entry,
+        Err(e) => {
+            return (StatusCode::BAD_REQUEST, Json(ApiResponse::<()>::error(e)));
+        }
+    };
+
+    enqueue(state, entry, req.now).await;
+    (StatusCode::OK, Json(ApiResponse::success(())))
+}
+
+/// DELETE /api/queue/{index}
+pub async fn remove_from_queue(
+    State(state): State<Arc<AppState>>,
+    AxPath(index): AxPath<usize>
+) -> impl IntoResponse {
+    if state.play_queue.write().await.remove(index).is_none() {
+        return (
+            StatusCode::NOT_FOUND,
+            Json(ApiResponse::<()>::error("Invalid queue index".to_string())),
+        );
+    }
+
+    (StatusCode::OK, Json(ApiResponse::success(())))
+}
+
+/// DELETE /api/queue
+pub async fn clear_queue(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    state.play_queue.write().await.clear();
+    Json(ApiResponse::success(()))
+}
+
+/// POST /api/stop
+pub async fn stop_playback(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    stop_streaming(state).await;
+    Json(ApiResponse::success(()))
+}
+
+/// POST /api/start-streaming
+pub async fn start_streaming(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    let shows = state.shows.read().await;
+    if shows.is_empty() {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(ApiResponse::<()>::error("No videos available. Please scan first.".to_string())),
+        );
+    }
+    drop(shows);
+
+    start_tv_loop_if_needed(state).await;
+    (StatusCode::OK, Json(ApiResponse::success(())))
+}
+
+/// POST /api/subtitle-mode
+pub async fn set_subtitle_mode(
+    State(state): State<Arc<AppState>>,
+    Json(req): Json<SetSubtitleModeRequest>
+) -> impl IntoResponse {
+    *state.subtitle_mode.write().await = req.mode.clone();
+
+    // Save config
+    if let Err(e) = save_config(&state).await {
+        return (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ApiResponse::<()>::error(format!("Failed to save config: {}", e))),
+        );
+    }
+
+    (StatusCode::OK, Json(ApiResponse::success(())))
+}
+
+/// POST /api/playlist/end-policy
+pub async fn set_playlist_end_policy(
+    State(state): State<Arc<AppState>>,
+    Json(req): Json<SetPlaylistEndPolicyRequest>
+) -> impl IntoResponse {
+    *state.playlist_end.write().await = req.policy;
+
+    if let Err(e) = save_config(&state).await {
+        return (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ApiResponse::<()>::error(format!("Failed to save config: {}", e))),
+        );
+    }
+
+    (StatusCode::OK, Json(ApiResponse::success(())))
+}
+
+/// POST /api/playlist/rotation
+pub async fn set_channel_rotation(
+    State(state): State<Arc<AppState>>,
+    Json(req): Json<SetChannelRotationRequest>
+) -> impl IntoResponse {
+    if req.rotation == ChannelRotation::Blocks(0) {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(ApiResponse::<()>::error("Blocks need at least 1 episode".to_string())),
+        );
+    }
+
+    *state.channel_rotation.write().await = req.rotation;
+
+    if let Err(e) = save_config(&state).await {
+        return (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ApiResponse::<()>::error(format!("Failed to save config: {}", e))),
+        );
+    }
+
+    (StatusCode::OK, Json(ApiResponse::success(())))
+}
+
+/// GET /api/now-next
+pub async fn get_now_next(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    let now_playing = state.now_playing.read().await.clone();
+    Json(ApiResponse::<Option<NowPlaying>>::success(now_playing))
+}
+
+fn cursor_response(show_name: &str, cursor: Option<&ShowCursor>, episodes: &[Episode]) -> CursorResponse {
+    let next = cursor.map_or(0, |cursor| next_position(cursor, episodes));
+    let next_episode = episodes.get(next);
+    CursorResponse {
+        show_name: show_name.to_string(),
+        cursor: cursor.cloned(),
+        next_position: next,
+        next_file_id: next_episode.map(|episode| episode.file_id.clone()),
+        next_episode: next_episode.map(|episode| episode.name.clone()),
+    }
+}
+
+fn unknown_channel<T>(channel: &str) -> (StatusCode, Json<ApiResponse<T>>) {
+    (StatusCode::NOT_FOUND, Json(ApiResponse::error(format!("Unknown channel: {}", channel))))
+}
+
+/// Move a show's cursor so that the episode at the index returned by `target`
+/// airs next, save the cursors and tell the channel to pick up the change
+async fn move_cursor(
+    state: &AppState,
+    channel: &str,
+    show_name: &str,
+    target: impl FnOnce(usize, &[Episode]) -> Result<usize, String>
+) -> (StatusCode, Json<ApiResponse<CursorResponse>>) {
+    if channel != TV_CHANNEL {
+        return unknown_channel(channel);
+    }
+
+    let response = {
+        let shows = state.shows.read().await;
+        let Some(episodes) = shows.get(show_name) else {
+            return (
+                StatusCode::NOT_FOUND,
+                Json(ApiResponse::error(format!("Show not found: {}", show_name))),
+            );
+        };
+
+        let mut cursors = state.cursors.write().await;
+        let show_cursors = cursors.entry(channel.to_string()).or_default();
+        let current = show_cursors.get(show_name).map_or(0, |cursor| next_position(cursor, episodes));
+        let next = match target(current, episodes) {
+            Ok(next) => next,
+            Err(e) => {
+                return (StatusCode::BAD_REQUEST, Json(ApiResponse::error(e)));
+            }
+        };
+
+        set_next_position(show_cursors, show_name, episodes, next);
+        cursor_response(show_name, show_cursors.get(show_name), episodes)
+    };
+    *state.cursors_changed.write().await = true;
+
+    if let Err(e) = save_cursors(state).await {
+        return (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ApiResponse::error(format!("Failed to save cursors: {}", e))),
+        );
+    }
+
+    (StatusCode::OK, Json(ApiResponse::success(response)))
+}
+
+/// GET /api/channels/{channel}/cursors
+pub async fn get_cursors(
+    State(state): State<Arc<AppState>>,
+    AxPath(channel): AxPath<String>
+) -> impl IntoResponse {
+    if channel != TV_CHANNEL {
+        return unknown_channel(&channel);
+    }
+
+    let shows = state.shows.read().await;
+    let cursors = state.cursors.read().await;
+    let mut response: Vec<CursorResponse> = cursors
+        .get(&channel)
+        .into_iter()
+        .flatten()
+        .map(|(show_name, cursor)| {
+            let episodes = shows.get(show_name).map(Vec::as_slice).unwrap_or_default();
+            cursor_response(show_name, Some(cursor), episodes)
+        })
+        .collect();
+    response.sort_by(|a, b| a.show_name.cmp(&b.show_name));
+
+    (StatusCode::OK, Json(ApiResponse::success(response)))
+}
+
+/// PUT /api/channels/{channel}/cursors/{show}
+pub async fn jump_cursor(
+    State(state): State<Arc<AppState>>,
+    AxPath((channel, show_name)): AxPath<(String, String)>,
+    Json(req): Json<JumpCursorRequest>
+) -> impl IntoResponse {
+    move_cursor(&state, &channel, &show_name, |_, episodes| {
+        match (req.file_id, req.position) {
+            (Some(file_id), _) =>
+                episodes
+                    .iter()
+                    .position(|episode| episode.file_id == file_id)
+                    .ok_or_else(|| format!("Episode not found in {}: {}", show_name, file_id)),
+            (None, Some(position)) if position <= episodes.len() => Ok(position),
+            (None, Some(position)) =>
+                Err(format!("{} only has {} episodes, got position {}", show_name, episodes.len(), position)),
+            (None, None) => Err("Either file_id or position is required".to_string()),
+        }
+    }).await
+}
+
+/// POST /api/channels/{channel}/cursors/{show}/rewind
+pub async fn rewind_cursor(
+    State(state): State<Arc<AppState>>,
+    AxPath((channel, show_name)): AxPath<(String, String)>,
+    Json(req): Json<RewindCursorRequest>
+) -> impl IntoResponse {
+    let count = req.count.unwrap_or(1);
+    move_cursor(&state, &channel, &show_name, |current, _| Ok(current.saturating_sub(count))).await
+}
+
+/// DELETE /api/channels/{channel}/cursors/{show}
+pub async fn reset_cursor(
+    State(state): State<Arc<AppState>>,
+    AxPath((channel, show_name)): AxPath<(String, String)>
+) -> impl IntoResponse {
+    move_cursor(&state, &channel, &show_name, |_, _| Ok(0)).await
+}
+
+/// Seconds from `ss`, `mm:ss` or `hh:mm:ss`; only the seconds may have a fraction
+fn parse_timestamp(value: &str) -> Option<f64> {
+    let parts: Vec<&str> = value.split(':').collect();
+    if parts.len() > 3 {
+        return None;
+    }
+
+    let mut seconds = 0.0;
+    for (i, part) in parts.iter().enumerate() {
+        let last = i == parts.len() - 1;
+        let number: f64 = part.trim().parse().ok()?;
+        if !number.is_finite() || number < 0.0 || (!last && number.fract() != 0.0) {
+            return None;
+        }
+        if i > 0 && number >= 60.0 {
+            return None;
+        }
+        seconds = seconds * 60.0 + number;
+    }
+    Some(seconds)
+}
+
+/// Hand a transport command to the running channel
+async fn send_channel_command(
+    state: &AppState,
+    channel: &str,
+    command: ChannelCommand
+) -> (StatusCode, Json<ApiResponse<()>>) {
+    if channel != TV_CHANNEL {
+        return unknown_channel(channel);
+    }
+
+    // Only a running encode takes commands; between files and in fallback
+    // mode there is no sender and the command is refused
+    let sent =
+        state.now_playing.read().await.is_some() &&
+        state.channel_commands
+            .read().await
+            .as_ref()
+            .is_some_and(|sender| sender.send(command).is_ok());
+    if !sent {
+        return (
+            StatusCode::CONFLICT,
+            Json(
+                ApiResponse::error(
+                    "The channel is not playing anything that can be controlled right now".to_string()
+                )
+            ),
+        );
+    }
+
+    (StatusCode::OK, Json(ApiResponse::success(())))
+}
+
+/// POST /api/channel/{id}/skip
+pub async fn skip_episode(
+    State(state): State<Arc<AppState>>,
+    AxPath(channel): AxPath<String>
+) -> impl IntoResponse {
+    send_channel_command(&state, &channel, ChannelCommand::Skip).await
+}
+
+/// POST /api/channel/{id}/previous
+pub async fn previous_episode(
+    State(state): State<Arc<AppState>>,
+    AxPath(channel): AxPath<String>
+) -> impl IntoResponse {
+    send_channel_command(&state, &channel, ChannelCommand::Previous).await
+}
+
+/// POST /api/channel/{id}/restart
+pub async fn restart_episode(
+    State(state): State<Arc<AppState>>,
+    AxPath(channel): AxPath<String>
+) -> impl IntoResponse {
+    send_channel_command(&state, &channel, ChannelCommand::Restart).await
+}
+
+/// POST /api/channel/{id}/seek?to=mm:ss
+pub async fn seek_episode(
+    State(state): State<Arc<AppState>>,
+    AxPath(channel): AxPath<String>,
+    Query(query): Query<SeekQuery>
+) -> impl IntoResponse {
+    let Some(to) = parse_timestamp(&query.to) else {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(ApiResponse::error(format!("Invalid position: {} (expected mm:ss)", query.to))),
+        );
+    };
+
+    let current = state.now_playing.read().await.as_ref().map(|now| PathBuf::from(&now.now.file_path));
+    let duration = match current {
+        Some(path) =>
+            state.shows
+                .read().await
+                .values()
+                .flatten()
+                .find(|episode| episode.file_path == path)
+                .and_then(|episode| episode.media_info.as_ref())
+                .and_then(|info| info.duration),
+        None => None,
+    };
+    if let Some(duration) = duration && to >= duration {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(ApiResponse::error(format!("The episode is only {:.0}s long", duration))),
+        );
+    }
+
+    send_channel_command(&state, &channel, ChannelCommand::Seek(to)).await
+}
+
+/// POST /api/watch
+pub async fn set_watch_library(
+    State(state): State<Arc<AppState>>,
+    Json(req): Json<SetWatchLibraryRequest>
+) -> impl IntoResponse {
+    *state.watch_library.write().await = req.enabled;
+    restart_library_watcher(state.clone()).await;
+
+    if let Err(e) = save_config(&state).await {
+        return (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ApiResponse::<()>::error(format!("Failed to save config: {}", e))),
+        );
+    }
+
+    (StatusCode::OK, Json(ApiResponse::success(())))
+}
+
+// Metadata Override Handlers
+
+/// GET /api/overrides
+pub async fn get_overrides(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    let overrides = state.overrides.read().await.clone();
+    Json(ApiResponse::success(overrides))
+}
+
+/// PUT /api/overrides/files/{file_id}
+pub async fn set_file_override(
+    State(state): State<Arc<AppState>>,
+    AxPath(file_id): AxPath<String>,
+    Json(req): Json<EpisodeOverride>
+) -> impl IntoResponse {
+    let known = state.shows
+        .read().await
+        .values()
+        .flatten()
+        .any(|episode| episode.file_id == file_id);
+    if !known {
+        return (
+            StatusCode::NOT_FOUND,
+            Json(ApiResponse::<Overrides>::error("No episode with this file id".to_string())),
+        );
+    }
+
+    state.overrides.write().await.files.insert(file_id, req);
+    override_response(&state).await
+}
+
+/// DELETE /api/overrides/files/{file_id}
+pub async fn remove_file_override(
+    State(state): State<Arc<AppState>>,
+    AxPath(file_id): AxPath<String>
+) -> impl IntoResponse {
+    if state.overrides.write().await.files.remove(&file_id).is_none() {
+        return (
+            StatusCode::NOT_FOUND,
+            Json(ApiResponse::<Overrides>::error("Override not found".to_string())),
+        );
+    }
+
+    override_response(&state).await
+}
+
+/// PUT /api/overrides/shows/{name} - renaming onto an existing show merges them
+pub async fn rename_show(
+    State(state): State<Arc<AppState>>,
+    AxPath(show_name): AxPath<String>,
+    Json(req): Json<RenameShowRequest>
+) -> impl IntoResponse {
+    let name = req.name.trim().to_string();
+    if name.is_empty() || name == show_name {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(ApiResponse::<Overrides>::error("Invalid show name".to_string())),
+        );
+    }
+
+    state.overrides.write().await.shows.insert(show_name, name);
+    override_response(&state).await
+}
+
+/// DELETE /api/overrides/shows/{name}
+pub async fn remove_show_rename(
+    State(state): State<Arc<AppState>>,
+    AxPath(show_name): AxPath<String>
+) -> impl IntoResponse {
+    if state.overrides.write().await.shows.remove(&show_name).is_none() {
+        return (
+            StatusCode::NOT_FOUND,
+            Json(ApiResponse::<Overrides>::error("Override not found".to_string())),
+        );
+    }
+
+    override_response(&state).await
+}
+
+/// Re-apply the changed overrides to the library, persist them and reply with the new set
+async fn override_response(state: &AppState) -> (StatusCode, Json<ApiResponse<Overrides>>) {
+    let roots = state.libraries.read().await.clone();
+    let rules = ParseRules::new(&state.parse_rules.read().await);
+    let overrides = state.overrides.read().await.clone();
+
+    {
+        let mut shows = state.shows.write().await;
+        *shows = relabel_library(&shows, &roots, &rules, &overrides);
+        *state.tv_files.write().await = available_files(&shows);
+        update_cursors(state, &shows).await;
+        *state.library_stats.write().await = None;
+
+        // Keep show artwork with a renamed show until the next scan refreshes it
+        let mut show_metadata = state.show_metadata.write().await;
+        for (from, to) in &overrides.shows {
+            if
+                !show_metadata.contains_key(to) &&
+                let Some(metadata) = show_metadata.get(from).cloned()
+            {
+                show_metadata.insert(to.clone(), metadata);
+            }
+        }
+        show_metadata.retain(|name, _| shows.contains_key(name));
+    }
+
+    if let Err(e) = save_config(state).await {
+        return (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ApiResponse::error(format!("Failed to save config: {}", e))),
+        );
+    }
+
+    (StatusCode::OK, Json(ApiResponse::success(overrides)))
+}
+
+// Playlist Management Handlers
+
+/// A trimmed name for a new playlist, unless it is empty
+fn new_playlist_name(name: &str) -> Result<String, String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("Playlist name cannot be empty".to_string());
+    }
+    Ok(name.to_string())
+}
+
+/// Refuse a taken name. Checked under the same `playlists` write lock as the
+/// insert, so two requests can't both claim a name.
+fn check_name_free(
+    active: &str,
+    playlists: &BTreeMap<String, Vec<PlaylistItem>>,
+    name: &str
+) -> Result<(), (StatusCode, Json<ApiResponse<()>>)> {
+    if active == name || playlists.contains_key(name) {
+        return Err((
+            StatusCode::CONFLICT,
+            Json(ApiResponse::error(format!("A playlist named {} already exists", name))),
+        ));
+    }
+    Ok(())
+}
+
+/// GET /api/playlists
+pub async fn get_playlists(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    let active = state.active_playlist.read().await.clone();
+    let mut playlists: BTreeMap<String, Vec<PlaylistItem>> = state.playlists.read().await.clone();
+    playlists.insert(active.clone(), state.playlist.read().await.clone());
+
+    let playlists: Vec<NamedPlaylist> = playlists
+        .into_iter()
+        .map(|(name, items)| NamedPlaylist { active: name == active, name, items })
+        .collect();
+    Json(ApiResponse::success(playlists))
+}
+
+/// POST /api/playlists
+pub async fn create_playlist(
+    State(state): State<Arc<AppState>>,
+    Json(mut req): Json<CreatePlaylistRequest>
+) -> impl IntoResponse {
+    let name = match new_playlist_name(&req.name) {
+        Ok(name) => name,
+        Err(e) => {
+            return (StatusCode::BAD_REQUEST, Json(ApiResponse::<()>::error(e)));
+        }
+    };
+    for (index, item) in req.items.iter_mut().enumerate() {
+        settle_seed(item);
+        if let Err(e) = validate_item(&state, item).await {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(ApiResponse::<()>::error(format!("Item {}: {}", index, e))),
+            );
+        }
+    }
+    {
+        let active = state.active_playlist.read().await;
+        let mut playlists = state.playlists.write().await;
+        if let Err(taken) = check_name_free(&active, &playlists, &name) {
+            return taken;
+        }
+        playlists.insert(name, req.items);
+    }
+
+    if let Err(e) = save_config(&state).await {
+        return (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ApiResponse::<()>::error(format!("Failed to save config: {}", e))),
+        );
+    }
 
     (StatusCode::OK, Json(ApiResponse::success(())))
 }
 
-/// DELETE /api/playlist
-pub async fn clear_playlist(State(state): State<Arc<AppState>>) -> impl IntoResponse {
-    state.playlist.write().await.clear();
+/// POST /api/playlists/{name}/duplicate
+pub async fn duplicate_playlist(
+    State(state): State<Arc<AppState>>,
+    AxPath(name): AxPath<String>,
+    Json(req): Json<PlaylistNameRequest>
+) -> impl IntoResponse {
+    let copy_name = match new_playlist_name(&req.name) {
+        Ok(copy_name) => copy_name,
+        Err(e) => {
+            return (StatusCode::BAD_REQUEST, Json(ApiResponse::<()>::error(e)));
+        }
+    };
 
-    // Save config
-    if let Err(e) = save_config_internal(state).await {
+    {
+        let active = state.active_playlist.read().await;
+        let mut playlists = state.playlists.write().await;
+        if let Err(taken) = check_name_free(&active, &playlists, &copy_name) {
+            return taken;
+        }
+        let items = if *active == name {
+            Some(state.playlist.read().await.clone())
+        } else {
+            playlists.get(&name).cloned()
+        };
+        let Some(items) = items else {
+            return (
+                StatusCode::NOT_FOUND,
+                Json(ApiResponse::<()>::error(format!("Playlist not found: {}", name))),
+            );
+        };
+        playlists.insert(copy_name, items);
+    }
+
+    if let Err(e) = save_config(&state).await {
+        return (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ApiResponse::<()>::error(format!("Failed to save config: {}", e))),
+        );
+    }
+
+    (StatusCode::OK, Json(ApiResponse::success(())))
+}
+
+/// POST /api/playlists/{name}/rename
+pub async fn rename_playlist(
+    State(state): State<Arc<AppState>>,
+    AxPath(name): AxPath<String>,
+    Json(req): Json<PlaylistNameRequest>
+) -> impl IntoResponse {
+    let new_name = match new_playlist_name(&req.name) {
+        Ok(new_name) => new_name,
+        Err(e) => {
+            return (StatusCode::BAD_REQUEST, Json(ApiResponse::<()>::error(e)));
+        }
+    };
+
+    {
+        let mut active = state.active_playlist.write().await;
+        let mut playlists = state.playlists.write().await;
+        if let Err(taken) = check_name_free(&active, &playlists, &new_name) {
+            return taken;
+        }
+        if *active == name {
+            *active = new_name;
+        } else if let Some(items) = playlists.remove(&name) {
+            playlists.insert(new_name, items);
+        } else {
+            return (
+                StatusCode::NOT_FOUND,
+                Json(ApiResponse::<()>::error(format!("Playlist not found: {}", name))),
+            );
+        }
+    }
+
+    if let Err(e) = save_config(&state).await {
+        return (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ApiResponse::<()>::error(format!("Failed to save config: {}", e))),
+        );
+    }
+
+    (StatusCode::OK, Json(ApiResponse::success(())))
+}
+
+/// DELETE /api/playlists/{name}
+pub async fn delete_playlist(
+    State(state): State<Arc<AppState>>,
+    AxPath(name): AxPath<String>
+) -> impl IntoResponse {
+    if *state.active_playlist.read().await == name {
+        return (
+            StatusCode::CONFLICT,
+            Json(ApiResponse::<()>::error("Activate another playlist before deleting this one".to_string())),
+        );
+    }
+    if state.playlists.write().await.remove(&name).is_none() {
+        return (
+            StatusCode::NOT_FOUND,
+            Json(ApiResponse::<()>::error(format!("Playlist not found: {}", name))),
+        );
+    }
+
+    if let Err(e) = save_config(&state).await {
         return (
             StatusCode::INTERNAL_SERVER_ERROR,
             Json(ApiResponse::<()>::error(format!("Failed to save config: {}", e))),
@@ -431,34 +1549,453 @@ pub async fn clear_playlist(State(state): State<Arc<AppState>>) -> impl IntoResp
     (StatusCode::OK, Json(ApiResponse::success(())))
 }
 
-// Helper Functions
+/// POST /api/channels/{channel}/playlist
+///
+/// The channel picks up the new playlist after the current episode, or right
+/// away with `immediate`. Items both playlists share keep their progress.
+pub async fn activate_playlist(
+    State(state): State<Arc<AppState>>,
+    AxPath(channel): AxPath<String>,
+    Json(req): Json<ActivatePlaylistRequest>
+) -> impl IntoResponse {
+    if channel != TV_CHANNEL {
+        return unknown_channel(&channel);
+    }
+
+    {
+        let mut active = state.active_playlist.write().await;
+        if *active == req.name {
+            return (StatusCode::OK, Json(ApiResponse::success(())));
+        }
+
+        let mut playlists = state.playlists.write().await;
+        let Some(items) = playlists.remove(&req.name) else {
+            return (
+                StatusCode::NOT_FOUND,
+                Json(ApiResponse::error(format!("Playlist not found: {}", req.name))),
+            );
+        };
+        let previous = mem::replace(&mut *state.playlist.write().await, items);
+        playlists.insert(mem::replace(&mut *active, req.name.clone()), previous);
+    }
+    println!("[playlist] Activated playlist {}", req.name);
+
+    // Queued files are left to finish either way
+    let playing_schedule = state.now_playing
+        .read().await
+        .as_ref()
+        .is_some_and(|now| now.item.is_some());
+    if
+        req.immediate &&
+        playing_schedule &&
+        let Some(sender) = state.channel_commands.read().await.as_ref()
+    {
+        let _ = sender.send(ChannelCommand::SwitchPlaylist);
+    }
+
+    if let Err(e) = save_config(&state).await {
+        return (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ApiResponse::error(format!("Failed to save config: {}", e))),
+        );
+    }
+
+    (StatusCode::OK, Json(ApiResponse::success(())))
+}
+
+/// ETag of a playlist. It covers the name too, so edits of the active
+/// playlist made before a switch are refused.
+fn playlist_etag(name: &str, playlist: &[PlaylistItem]) -> String {
+    let items = serde_json::to_vec(playlist).unwrap_or_default();
+    let hash = fnv1a(name.as_bytes().iter().chain(&[0]).chain(&items));
+    format!("\"{:016x}\"", hash)
+}
+
+/// Whether an `If-Match` header rules out the playlist version `etag`.
+/// Requests without one always go through.
+fn precondition_failed(headers: &HeaderMap, etag: &str) -> bool {
+    let Some(value) = headers.get(header::IF_MATCH).and_then(|value| value.to_str().ok()) else {
+        return false;
+    };
+    !value
+        .split(',')
+        .map(|tag| tag.trim().trim_start_matches("W/"))
+        .any(|tag| tag == "*" || tag == etag)
+}
+
+fn playlist_not_found(name: &str) -> Response {
+    (
+        StatusCode::NOT_FOUND,
+        Json(ApiResponse::<()>::error(format!("Playlist not found: {}", name))),
+    ).into_response()
+}
+
+/// Name and items of the playlist called `name`, or of the active one
+async fn playlist_snapshot(state: &AppState, name: Option<&str>) -> Option<(String, Vec<PlaylistItem>)> {
+    let active = state.active_playlist.read().await;
+    match name {
+        Some(name) if name != *active => {
+            let items = state.playlists.read().await.get(name)?.clone();
+            Some((name.to_string(), items))
+        }
+        _ => Some((active.clone(), state.playlist.read().await.clone())),
+    }
+}
 
-async fn save_config_internal(state: Arc<AppState>) -> Result<(), String> {
-    let exe_dir = std::env
-        ::current_exe()
-        .map_err(|e| format!("Failed to get executable path: {}", e))?
-        .parent()
-        .ok_or_else(|| "Failed to get executable directory".to_string())?
-        .to_path_buf();
+/// Apply `edit` to the playlist called `name` (the active one if `None`) if
+/// `If-Match` allows it, then save. Successful responses carry the new ETag.
+async fn edit_playlist<T: Serialize>(
+    state: &AppState,
+    name: Option<&str>,
+    headers: &HeaderMap,
+    edit: impl FnOnce(&mut Vec<PlaylistItem>) -> Result<T, (StatusCode, String)>
+) -> Response {
+    let (data, etag) = {
+        // Same lock order as activating a playlist
+        let active = state.active_playlist.read().await;
+        let name = name.unwrap_or(&active);
+        let mut active_items;
+        let mut others;
+        let playlist = if name == *active {
+            active_items = state.playlist.write().await;
+            &mut *active_items
+        } else {
+            others = state.playlists.write().await;
+            match others.get_mut(name) {
+                Some(items) => items,
+                None => {
+                    return playlist_not_found(name);
+                }
+            }
+        };
 
-    let config_path = exe_dir.join("config.yml");
+        let current = playlist_etag(name, playlist);
+        if precondition_failed(headers, &current) {
+            return (
+                StatusCode::PRECONDITION_FAILED,
+                [(header::ETAG, current)],
+                Json(
+                    ApiResponse::<()>::error(
+                        "The playlist was changed elsewhere, reload it and try again".to_string()
+                    )
+                ),
+            ).into_response();
+        }
 
-    let config = AppConfig {
-        videos_folder: state.videos_folder.read().await.clone(),
-        shows: state.shows.read().await.clone(),
-        playlist: state.playlist.read().await.clone(),
-        played_episodes: state.played_episodes.read().await.clone(),
-        subtitle_mode: state.subtitle_mode.read().await.clone(),
+        match edit(playlist) {
+            Ok(data) => (data, playlist_etag(name, playlist)),
+            Err((status, e)) => {
+                return (status, Json(ApiResponse::<()>::error(e))).into_response();
+            }
+        }
     };
 
-    let yaml = serde_yaml
-        ::to_string(&config)
-        .map_err(|e| format!("Failed to serialize config: {}", e))?;
+    if let Err(e) = save_config(state).await {
+        return (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ApiResponse::<()>::error(format!("Failed to save config: {}", e))),
+        ).into_response();
+    }
+
+    (StatusCode::OK, [(header::ETAG, etag)], Json(ApiResponse::success(data))).into_response()
+}
+
+/// Shuffled items keep a seed so their order survives restarts; other orders drop it
+fn settle_seed(item: &mut PlaylistItem) {
+    match item.order {
+        PlayOrder::Shuffle => {
+            item.seed.get_or_insert_with(random_seed);
+        }
+        _ => {
+            item.seed = None;
+        }
+    }
+}
+
+/// Check an item before it goes into the playlist
+async fn validate_item(state: &AppState, item: &PlaylistItem) -> Result<(), String> {
+    if item.play_mode() == PlayMode::Repeat && item.repeat_count == 0 {
+        return Err("Repeat mode needs a repeat_count of at least 1".to_string());
+    }
+    if item.order == PlayOrder::Pick(0) {
+        return Err("Pick needs a count of at least 1".to_string());
+    }
+    if item.weight == Some(0) {
+        return Err("Weight must be at least 1".to_string());
+    }
+    if item.episode_range.is_some() {
+        return Err("episode_range is no longer supported, use range (e.g. \"S1E5-S2E3\")".to_string());
+    }
 
-    tokio::fs
-        ::write(&config_path, yaml).await
-        .map_err(|e| format!("Failed to write config file: {}", e))?;
+    let episodes = {
+        let shows = state.shows.read().await;
+        let show_metadata = state.show_metadata.read().await;
+        playlist_episodes(item, &shows, &show_metadata)
+    };
+    if episodes.is_empty() {
+        return Err(
+            if item.collection.is_some() || item.genre.is_some() {
+                format!("Nothing in the library matches {}", item.label())
+            } else {
+                "Show not found".to_string()
+            }
+        );
+    }
 
-    println!("Configuration saved to {}", config_path.display());
+    // Every number has to exist rather than silently selecting nothing
+    let spans: Vec<_> = episodes.iter().filter_map(EpisodeRef::span).collect();
+    let named = item.range
+        .iter()
+        .flat_map(|range| [range.from, range.to])
+        .chain(item.episodes.iter().copied())
+        .chain(item.exclude.iter().copied());
+    for wanted in named {
+        if !spans.iter().any(|span| span.contains(&wanted)) {
+            return Err(format!("There is no episode {} in {}", wanted, item.label()));
+        }
+    }
+    if !episodes.iter().any(|episode| item.selects(episode)) {
+        return Err(format!("The episode selection of {} leaves nothing to play", item.label()));
+    }
     Ok(())
 }
+
+fn invalid_index() -> (StatusCode, String) {
+    (StatusCode::BAD_REQUEST, "Invalid playlist index".to_string())
+}
+
+// The `/api/playlist/...` routes edit the active playlist. Each also exists
+// as `/api/playlists/{name}/items/...` for any playlist, active or not.
+
+/// GET /api/playlist - the ETag header versions the playlist for `If-Match`
+pub async fn get_playlist(
+    State(state): State<Arc<AppState>>,
+    name: Option<AxPath<String>>
+) -> impl IntoResponse {
+    let name = name.map(|AxPath(name)| name);
+    let Some((name, playlist)) = playlist_snapshot(&state, name.as_deref()).await else {
+        return playlist_not_found(name.as_deref().unwrap_or_default());
+    };
+    let etag = playlist_etag(&name, &playlist);
+    ([(header::ETAG, etag)], Json(ApiResponse::success(playlist))).into_response()
+}
+
+/// PUT /api/playlist - replace every item
+pub async fn replace_playlist(
+    State(state): State<Arc<AppState>>,
+    name: Option<AxPath<String>>,
+    headers: HeaderMap,
+    Json(mut items): Json<Vec<PlaylistItem>>
+) -> impl IntoResponse {
+    for (index, item) in items.iter_mut().enumerate() {
+        settle_seed(item);
+        if let Err(e) = validate_item(&state, item).await {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(ApiResponse::<()>::error(format!("Item {}: {}", index, e))),
+            ).into_response();
+        }
+    }
+
+    let name = name.map(|AxPath(name)| name);
+    edit_playlist(&state, name.as_deref(), &headers, |playlist| {
+        *playlist = items;
+        Ok(playlist.clone())
+    }).await
+}
+
+/// POST /api/playlist/add
+pub async fn add_to_playlist(
+    State(state): State<Arc<AppState>>,
+    name: Option<AxPath<String>>,
+    headers: HeaderMap,
+    Json(req): Json<AddToPlaylistRequest>
+) -> impl IntoResponse {
+    let mut item = PlaylistItem {
+        show_name: req.show_name.unwrap_or_default(),
+        collection: req.collection,
+        genre: req.genre,
+        episode_range: None,
+        range: req.range,
+        episodes: req.episodes,
+        exclude: req.exclude,
+        repeat_count: req.repeat_count.unwrap_or(0),
+        mode: req.mode,
+        order: req.order.unwrap_or_default(),
+        seed: req.seed,
+        weight: req.weight,
+    };
+    settle_seed(&mut item);
+
+    if let Err(e) = validate_item(&state, &item).await {
+        return (StatusCode::BAD_REQUEST, Json(ApiResponse::<()>::error(e))).into_response();
+    }
+
+    let name = name.map(|AxPath(name)| name);
+    edit_playlist(&state, name.as_deref(), &headers, |playlist| {
+        playlist.push(item);
+        Ok(())
+    }).await
+}
+
+/// PATCH /api/playlist/{index} - change an item's episodes, repeat, mode, order or weight
+pub async fn update_playlist_item(
+    State(state): State<Arc<AppState>>,
+    AxPath(path): AxPath<PlaylistItemPath>,
+    headers: HeaderMap,
+    Json(req): Json<UpdatePlaylistItemRequest>
+) -> impl IntoResponse {
+    let Some((_, items)) = playlist_snapshot(&state, path.name.as_deref()).await else {
+        return playlist_not_found(path.name.as_deref().unwrap_or_default());
+    };
+    let index = path.index;
+    let Some(original) = items.get(index).cloned() else {
+        let (status, e) = invalid_index();
+        return (status, Json(ApiResponse::<()>::error(e))).into_response();
+    };
+
+    let mut item = original.clone();
+    if let Some(range) = req.range {
+        item.range = range;
+    }
+    if let Some(episodes) = req.episodes {
+        item.episodes = episodes;
+    }
+    if let Some(exclude) = req.exclude {
+        item.exclude = exclude;
+    }
+    if let Some(repeat_count) = req.repeat_count {
+        item.repeat_count = repeat_count;
+    }
+    if let Some(mode) = req.mode {
+        item.mode = mode;
+    }
+    if let Some(order) = req.order {
+        item.order = order;
+    }
+    if let Some(seed) = req.seed {
+        item.seed = Some(seed);
+    }
+    if let Some(weight) = req.weight {
+        item.weight = weight;
+    }
+    settle_seed(&mut item);
+
+    if let Err(e) = validate_item(&state, &item).await {
+        return (StatusCode::BAD_REQUEST, Json(ApiResponse::<()>::error(e))).into_response();
+    }
+
+    edit_playlist(&state, path.name.as_deref(), &headers, |playlist| {
+        // Without `If-Match` the item could have changed while it was validated
+        match playlist.get_mut(index) {
+            Some(current) if *current == original => {
+                *current = item.clone();
+                Ok(item)
+            }
+            _ => Err((StatusCode::CONFLICT, "The playlist item was changed meanwhile".to_string())),
+        }
+    }).await
+}
+
+/// DELETE /api/playlist/{index}
+pub async fn remove_from_playlist(
+    State(state): State<Arc<AppState>>,
+    AxPath(path): AxPath<PlaylistItemPath>,
+    headers: HeaderMap
+) -> impl IntoResponse {
+    let index = path.index;
+    edit_playlist(&state, path.name.as_deref(), &headers, |playlist| {
+        if index >= playlist.len() {
+            return Err(invalid_index());
+        }
+        playlist.remove(index);
+        Ok(())
+    }).await
+}
+
+/// POST /api/playlist/{index}/reshuffle - new seed for a shuffled item
+pub async fn reshuffle_playlist_item(
+    State(state): State<Arc<AppState>>,
+    AxPath(path): AxPath<PlaylistItemPath>,
+    headers: HeaderMap
+) -> impl IntoResponse {
+    let index = path.index;
+    edit_playlist(&state, path.name.as_deref(), &headers, |playlist| {
+        let Some(item) = playlist.get_mut(index) else {
+            return Err(invalid_index());
+        };
+        if item.order != PlayOrder::Shuffle {
+            return Err((StatusCode::BAD_REQUEST, "Playlist item is not shuffled".to_string()));
+        }
+        item.seed = Some(random_seed());
+        Ok(item.clone())
+    }).await
+}
+
+/// POST /api/playlist/{index}/move - move an item to position `to`
+pub async fn move_playlist_item_to(
+    State(state): State<Arc<AppState>>,
+    AxPath(path): AxPath<PlaylistItemPath>,
+    headers: HeaderMap,
+    Json(req): Json<MovePlaylistItemToRequest>
+) -> impl IntoResponse {
+    let index = path.index;
+    edit_playlist(&state, path.name.as_deref(), &headers, |playlist| {
+        if index >= playlist.len() || req.to >= playlist.len() {
+            return Err(invalid_index());
+        }
+        let item = playlist.remove(index);
+        playlist.insert(req.to, item);
+        Ok(())
+    }).await
+}
+
+/// POST /api/playlist/move
+pub async fn move_playlist_item(
+    State(state): State<Arc<AppState>>,
+    name: Option<AxPath<String>>,
+    headers: HeaderMap,
+    Json(req): Json<MovePlaylistItemRequest>
+) -> impl IntoResponse {
+    let name = name.map(|AxPath(name)| name);
+    edit_playlist(&state, name.as_deref(), &headers, |playlist| {
+        if req.index >= playlist.len() {
+            return Err(invalid_index());
+        }
+
+        match req.direction.as_str() {
+            "up" => {
+                if req.index == 0 {
+                    return Err((StatusCode::BAD_REQUEST, "Cannot move first item up".to_string()));
+                }
+                playlist.swap(req.index - 1, req.index);
+            }
+            "down" => {
+                if req.index >= playlist.len() - 1 {
+                    return Err((StatusCode::BAD_REQUEST, "Cannot move last item down".to_string()));
+                }
+                playlist.swap(req.index, req.index + 1);
+            }
+            _ => {
+                return Err((
+                    StatusCode::BAD_REQUEST,
+                    "Invalid direction. Use 'up' or 'down'".to_string(),
+                ));
+            }
+        }
+        Ok(())
+    }).await
+}
+
+/// DELETE /api/playlist
+pub async fn clear_playlist(
+    State(state): State<Arc<AppState>>,
+    name: Option<AxPath<String>>,
+    headers: HeaderMap
+) -> impl IntoResponse {
+    let name = name.map(|AxPath(name)| name);
+    edit_playlist(&state, name.as_deref(), &headers, |playlist| {
+        playlist.clear();
+        Ok(())
+    }).await
+}
@@ -0,0 +1,575 @@
+use std::{ collections::{ HashMap, HashSet, VecDeque }, mem, path::{ Path, PathBuf } };
+
+use crate::models::{ ChannelRotation, Episode, MediaMetadata, PlayMode, PlayOrder, PlaylistItem };
+use crate::order::{ order_pass, random_seed, SplitMix64 };
+use crate::video::playlist_episodes;
+
+/// Aired episodes kept for going back with `previous`
+const HISTORY_LIMIT: usize = 50;
+
+/// Where the channel is within one playlist item
+#[derive(Clone)]
+pub struct ItemCursor {
+    /// The item as it was when the cursor started; an edited item starts over
+    item: PlaylistItem,
+    /// Current pass over the item's episodes, in play order
+    queue: Vec<Episode>,
+    position: usize,
+    /// Completed passes
+    passes: usize,
+    /// A pass is loaded and not yet played to the end
+    loaded: bool,
+    finished: bool,
+}
+
+impl ItemCursor {
+    fn new(item: PlaylistItem) -> Self {
+        Self {
+            item,
+            queue: Vec::new(),
+            position: 0,
+            passes: 0,
+            loaded: false,
+            finished: false,
+        }
+    }
+
+    fn needs_pass(&self) -> bool {
+        !self.finished && !self.loaded
+    }
+
+    /// An empty pass finishes the item, otherwise it would be picked forever
+    fn start_pass(&mut self, queue: Vec<Episode>) {
+        self.finished = queue.is_empty();
+        self.loaded = !queue.is_empty();
+        self.queue = queue;
+        self.position = 0;
+    }
+
+    fn advance(&mut self) -> Option<Episode> {
+        let episode = self.queue.get(self.position)?.clone();
+        self.position += 1;
+        if self.position >= self.queue.len() {
+            self.end_pass();
+        }
+        Some(episode)
+    }
+
+    fn end_pass(&mut self) {
+        self.passes += 1;
+        self.loaded = false;
+        self.finished = match self.item.play_mode() {
+            PlayMode::Once | PlayMode::OnceThenStop => true,
+            PlayMode::Repeat => self.passes >= self.item.repeat_count,
+            PlayMode::Loop => false,
+        };
+    }
+}
+
+/// Per-item cursors plus the state of the channel's rotation between items.
+/// Each item continues where it left off when the rotation comes back to it.
+#[derive(Clone)]
+pub struct Rotation {
+    cursors: Vec<ItemCursor>,
+    current: usize,
+    /// Episodes played from `current` since the rotation last moved on
+    in_block: usize,
+    rng: SplitMix64,
+    /// Episodes to play before asking the rotation again, with their item
+    /// and the offset to start from
+    pending: VecDeque<(usize, Episode, f64)>,
+    /// Aired episodes with their item, most recent last
+    history: Vec<(usize, Episode)>,
+}
+
+impl Rotation {
+    pub fn new() -> Self {
+        Self {
+            cursors: Vec::new(),
+            current: 0,
+            in_block: 0,
+            rng: SplitMix64::new(random_seed()),
+            pending: VecDeque::new(),
+            history: Vec::new(),
+        }
+    }
+
+    /// Match cursors to the current playlist. Unchanged items keep their
+    /// cursor, even when moved; new or edited items start from the beginning.
+    pub fn sync(&mut self, playlist: &[PlaylistItem]) {
+        // Pending and past episodes refer to items by index
+        let unchanged = self.cursors.len() == playlist.len() &&
+            self.cursors.iter().zip(playlist).all(|(cursor, item)| &cursor.item == item);
+        if !unchanged {
+            self.pending.clear();
+            self.history.clear();
+        }
+
+        let mut previous: Vec<Option<ItemCursor>> = mem
+            ::take(&mut self.cursors)
+            .into_iter()
+            .map(Some)
+            .collect();
+
+        // The rotation stays with the item it was playing, wherever it moved
+        let mut current = None;
+        self.cursors = playlist
+            .iter()
+            .enumerate()
+            .map(|(index, item)| {
+                let from = previous
+                    .iter()
+                    .position(|cursor| cursor.as_ref().is_some_and(|cursor| &cursor.item == item));
+                match from.and_then(|from| Some((from, previous[from].take()?))) {
+                    Some((from, cursor)) => {
+                        if from == self.current {
+                            current = Some(index);
+                        }
+                        cursor
+                    }
+                    None => ItemCursor::new(item.clone()),
+                }
+            })
+            .collect();
+
+        match current {
+            Some(index) => {
+                self.current = index;
+            }
+            None => {
+                if self.current >= self.cursors.len() {
+                    self.current = 0;
+                }
+                self.in_block = 0;
+            }
+        }
+    }
+
+    /// Load the next pass of every item that has played its current one.
+    /// `Once` items leave out episodes already aired according to the cursors.
+    pub fn load_passes(
+        &mut self,
+        shows: &HashMap<String, Vec<Episode>>,
+        show_metadata: &HashMap<String, MediaMetadata>,
+        broken: &HashSet<PathBuf>,
+        aired: &HashSet<PathBuf>
+    ) {
+        for cursor in self.cursors.iter_mut().filter(|cursor| cursor.needs_pass()) {
+            let item = &cursor.item;
+            let episodes = playlist_episodes(item, shows, show_metadata);
+            if episodes.is_empty() {
+                eprintln!("[tv] No episodes found for {}. Please scan for videos first.", item.label());
+            }
+
+            let selected: Vec<&Episode> = episodes.iter().filter(|episode| item.selects(episode)).collect();
+            if selected.is_empty() && !episodes.is_empty() {
+                eprintln!("[tv] No episodes of {} match its episode selection", item.label());
+            }
+
+            let continues = item.play_mode() == PlayMode::Once;
+            let queue = order_pass(item, selected, |episode| {
+                if !episode.available {
+                    println!("[playlist] Skipping unavailable episode: {}", episode.name);
+                    return false;
+                }
+                if episode.hidden {
+                    return false;
+                }
+                if broken.contains(&episode.file_path) {
+                    println!("[playlist] Skipping known-bad file: {}", episode.name);
+                    return false;
+                }
+                !(continues && aired.contains(&episode.file_path))
+            });
+
+            cursor.start_pass(queue.into_iter().cloned().collect());
+        }
+    }
+
+    pub fn needs_passes(&self) -> bool {
+        self.cursors.iter().any(ItemCursor::needs_pass)
+    }
+
+    pub fn is_item_finished(&self, index: usize) -> bool {
+        self.cursors.get(index).is_none_or(|cursor| cursor.finished)
+    }
+
+    /// Start a new cycle over the playlist
+    pub fn restart(&mut self) {
+        for cursor in &mut self.cursors {
+            *cursor = ItemCursor::new(cursor.item.clone());
+        }
+        self.current = 0;
+        self.in_block = 0;
+    }
+
+    /// Item to play from next, or `None` once every item has finished
+    pub fn pick(&mut self, rotation: ChannelRotation) -> Option<usize> {
+        let block = match rotation {
+            ChannelRotation::Sequential => usize::MAX,
+            ChannelRotation::RoundRobin => 1,
+            ChannelRotation::Blocks(size) => size.max(1),
+            ChannelRotation::Weighted => {
+                return self.pick_weighted();
+            }
+        };
+
+        if self.is_item_finished(self.current) || self.in_block >= block {
+            let count = self.cursors.len();
+            self.current = (1..=count)
+                .map(|step| (self.current + step) % count)
+                .find(|&index| !self.cursors[index].finished)?;
+            self.in_block = 0;
+        }
+        Some(self.current)
+    }
+
+    fn pick_weighted(&mut self) -> Option<usize> {
+        let weight = |cursor: &ItemCursor| cursor.item.weight.unwrap_or(1).max(1) as usize;
+        let total: usize = self.cursors
+            .iter()
+            .filter(|cursor| !cursor.finished)
+            .map(weight)
+            .sum();
+        if total == 0 {
+            return None;
+        }
+
+        let mut roll = self.rng.below(total);
+        for (index, cursor) in self.cursors.iter().enumerate().filter(|(_, c)| !c.finished) {
+            if roll < weight(cursor) {
+                self.current = index;
+                return Some(index);
+            }
+            roll -= weight(cursor);
+        }
+        None
+    }
+
+    /// Continue with the episode at `file_path` from whichever item has it in
+    /// its current pass. Items with a lasting order (sequential or seeded
+    /// shuffle) skip ahead to it, random orders move it to the front of what
+    /// is left. Returns the item, if any.
+    pub fn resume_at(&mut self, file_path: &Path) -> Option<usize> {
+        let (index, at) = self.cursors
+            .iter()
+            .enumerate()
+            .filter(|(_, cursor)| cursor.loaded)
+            .find_map(|(index, cursor)| {
+                let at = cursor.queue[cursor.position..]
+                    .iter()
+                    .position(|episode| episode.file_path == file_path)?;
+                Some((index, cursor.position + at))
+            })?;
+
+        let cursor = &mut self.cursors[index];
+        if matches!(cursor.item.order, PlayOrder::Sequential | PlayOrder::Shuffle) {
+            cursor.position = at;
+        } else {
+            let episode = cursor.queue.remove(at);
+            cursor.queue.insert(cursor.position, episode);
+        }
+        self.current = index;
+        self.in_block = 0;
+        Some(index)
+    }
+
+    /// Episode put back by `go_back` or `interrupt`, to play before the
+    /// rotation continues
+    pub fn take_pending(&mut self) -> Option<(usize, Episode, f64)> {
+        self.pending.pop_front()
+    }
+
+    /// Continue `episode` of item `index` from `offset` before anything else
+    pub fn interrupt(&mut self, index: usize, episode: Episode, offset: f64) {
+        self.pending.push_front((index, episode, offset));
+    }
+
+    pub fn has_pending(&self) -> bool {
+        !self.pending.is_empty()
+    }
+
+    /// Remember an episode that has aired, for `go_back`
+    pub fn remember(&mut self, index: usize, episode: Episode) {
+        if self.history.len() >= HISTORY_LIMIT {
+            self.history.remove(0);
+        }
+        self.history.push((index, episode));
+    }
+
+    /// Play the previously aired episode next, followed by the interrupted
+    /// `current` one. Returns `false` if nothing has aired yet.
+    pub fn go_back(&mut self, index: usize, current: Episode) -> bool {
+        let Some((previous_index, previous)) = self.history.pop() else {
+            return false;
+        };
+        self.pending.push_front((index, current, 0.0));
+        self.pending.push_front((previous_index, previous, 0.0));
+        true
+    }
+
+    /// Take the next episode of item `index`
+    pub fn next_episode(&mut self, index: usize) -> Option<Episode> {
+        let episode = self.cursors.get_mut(index)?.advance()?;
+        self.in_block += 1;
+        Some(episode)
+    }
+
+    /// What the rotation would play next, assuming nothing changes meanwhile.
+    /// Passes that still have to be loaded are left out.
+    pub fn preview(&self, rotation: ChannelRotation, count: usize) -> Vec<Episode> {
+        let mut upcoming = self.clone();
+        let mut episodes: Vec<Episode> = upcoming.pending
+            .drain(..)
+            .map(|(_, episode, _)| episode)
+            .take(count)
+            .collect();
+        while episodes.len() < count {
+            let Some(index) = upcoming.pick(rotation) else {
+                break;
+            };
+            let cursor = &mut upcoming.cursors[index];
+            if !cursor.loaded {
+                cursor.finished = true;
+                continue;
+            }
+            if let Some(episode) = upcoming.next_episode(index) {
+                episodes.push(episode);
+            }
+        }
+        episodes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cursors::{ aired_files, forget_aired, TV_CHANNEL };
+    use crate::models::fixtures::{ item, library };
+    use crate::models::Cursors;
+
+    fn rotation(playlist: &[PlaylistItem]) -> Rotation {
+        let mut rotation = Rotation::new();
+        rotation.rng = SplitMix64::new(42);
+        rotation.sync(playlist);
+        rotation
+    }
+
+    /// Play up to `count` episodes the way the channel does, by name
+    fn play(
+        rotation: &mut Rotation,
+        mode: ChannelRotation,
+        shows: &HashMap<String, Vec<Episode>>,
+        count: usize
+    ) -> Vec<String> {
+        let mut played = Vec::new();
+        while played.len() < count {
+            if rotation.needs_passes() {
+                rotation.load_passes(shows, &HashMap::new(), &HashSet::new(), &HashSet::new());
+            }
+            let Some(index) = rotation.pick(mode) else {
+                break;
+            };
+            if let Some(episode) = rotation.next_episode(index) {
+                played.push(episode.name);
+            }
+        }
+        played
+    }
+
+    #[test]
+    fn picks_items_by_rotation_mode() {
+        let shows = library(&[("A", 3), ("B", 2)]);
+        let playlist = [item("A"), item("B")];
+        let cases = [
+            (ChannelRotation::Sequential, vec!["A S1E1", "A S1E2", "A S1E3", "B S1E1", "B S1E2"]),
+            (ChannelRotation::RoundRobin, vec!["A S1E1", "B S1E1", "A S1E2", "B S1E2", "A S1E3"]),
+            (ChannelRotation::Blocks(2), vec!["A S1E1", "A S1E2", "B S1E1", "B S1E2", "A S1E3"]),
+            (ChannelRotation::Blocks(0), vec!["A S1E1", "B S1E1", "A S1E2", "B S1E2", "A S1E3"]),
+        ];
+
+        for (mode, expected) in cases {
+            let mut rotation = rotation(&playlist);
+            assert_eq!(play(&mut rotation, mode, &shows, 10), expected, "{:?}", mode);
+            assert_eq!(rotation.pick(mode), None, "{:?} after the last episode", mode);
+        }
+    }
+
+    #[test]
+    fn weighted_picks_follow_the_weights() {
+        let shows = library(&[("A", 4), ("B", 4)]);
+        let mut heavy = item("A");
+        heavy.mode = Some(PlayMode::Loop);
+        heavy.weight = Some(3);
+        let mut light = item("B");
+        light.mode = Some(PlayMode::Loop);
+
+        let mut rotation = rotation(&[heavy.clone(), light.clone()]);
+        let played = play(&mut rotation, ChannelRotation::Weighted, &shows, 400);
+        let heavy_count = played.iter().filter(|name| name.starts_with('A')).count();
+        assert!((270..=330).contains(&heavy_count), "A played {} of 400 times", heavy_count);
+
+        // The same seed rolls the same schedule
+        let mut again = self::rotation(&[heavy, light]);
+        assert_eq!(play(&mut again, ChannelRotation::Weighted, &shows, 400), played);
+    }
+
+    #[test]
+    fn weighted_skips_finished_items() {
+        let shows = library(&[("A", 2), ("B", 2)]);
+        let mut heavy = item("A");
+        heavy.weight = Some(100);
+
+        let mut rotation = rotation(&[heavy, item("B")]);
+        let mut played = play(&mut rotation, ChannelRotation::Weighted, &shows, 10);
+        played.sort();
+        assert_eq!(played, ["A S1E1", "A S1E2", "B S1E1", "B S1E2"]);
+        assert_eq!(rotation.pick(ChannelRotation::Weighted), None);
+    }
+
+    #[test]
+    fn keeps_item_cursors_across_playlist_edits() {
+        let shows = library(&[("A", 3), ("B", 3), ("C", 3)]);
+        let mut rotation = rotation(&[item("A"), item("B")]);
+        assert_eq!(
+            play(&mut rotation, ChannelRotation::RoundRobin, &shows, 3),
+            ["A S1E1", "B S1E1", "A S1E2"]
+        );
+
+        // Moved items continue where they were, new and edited ones start over
+        let mut edited = item("B");
+        edited.repeat_count = 2;
+        edited.mode = Some(PlayMode::Repeat);
+        rotation.sync(&[item("C"), edited, item("A")]);
+        assert_eq!(
+            play(&mut rotation, ChannelRotation::Sequential, &shows, 6),
+            ["A S1E3", "C S1E1", "C S1E2", "C S1E3", "B S1E1", "B S1E2"]
+        );
+        assert_eq!(
+            play(&mut rotation, ChannelRotation::Sequential, &shows, 10),
+            ["B S1E3", "B S1E1", "B S1E2", "B S1E3"]
+        );
+    }
+
+    #[test]
+    fn playlist_edits_drop_pending_episodes() {
+        let shows = library(&[("A", 3)]);
+        let mut rotation = rotation(&[item("A")]);
+        play(&mut rotation, ChannelRotation::Sequential, &shows, 1);
+        let episode = rotation.next_episode(0).unwrap();
+
+        rotation.interrupt(0, episode.clone(), 12.0);
+        rotation.sync(&[item("A")]);
+        assert!(rotation.has_pending(), "an unchanged playlist keeps the interrupted episode");
+
+        rotation.sync(&[item("A"), item("A")]);
+        assert!(!rotation.has_pending());
+    }
+
+    #[test]
+    fn exhausts_and_restarts() {
+        let shows = library(&[("A", 2), ("B", 1)]);
+        let mut repeated = item("B");
+        repeated.mode = Some(PlayMode::Repeat);
+        repeated.repeat_count = 2;
+
+        let mut rotation = rotation(&[item("A"), repeated]);
+        assert_eq!(
+            play(&mut rotation, ChannelRotation::Sequential, &shows, 10),
+            ["A S1E1", "A S1E2", "B S1E1", "B S1E1"]
+        );
+        assert!(rotation.is_item_finished(0) && rotation.is_item_finished(1));
+        assert_eq!(rotation.pick(ChannelRotation::Sequential), None);
+        assert!(rotation.preview(ChannelRotation::Sequential, 5).is_empty());
+
+        rotation.restart();
+        assert_eq!(play(&mut rotation, ChannelRotation::Sequential, &shows, 1), ["A S1E1"]);
+    }
+
+    #[test]
+    fn once_items_start_over_once_their_aired_episodes_are_forgotten() {
+        let shows = library(&[("A", 2), ("B", 1)]);
+        let mut rotation = rotation(&[item("A"), item("B")]);
+        let mut cursors = Cursors::new();
+        for name in play(&mut rotation, ChannelRotation::Sequential, &shows, 10) {
+            // What `record_airing` keeps of a finished episode
+            let episode = shows.values().flatten().find(|episode| episode.name == name).unwrap();
+            cursors
+                .entry(TV_CHANNEL.to_string())
+                .or_default()
+                .entry(episode.show_name.clone())
+                .or_default()
+                .aired.get_or_insert_default()
+                .insert(episode.file_id.clone());
+        }
+
+        // The channel loop at the end of the playlist
+        let start_over = |rotation: &mut Rotation, cursors: &Cursors| {
+            rotation.restart();
+            let aired = aired_files(cursors.get(TV_CHANNEL), &shows);
+            rotation.load_passes(&shows, &HashMap::new(), &HashSet::new(), &aired);
+            play(rotation, ChannelRotation::Sequential, &shows, 10)
+        };
+        assert!(start_over(&mut rotation, &cursors).is_empty());
+
+        assert!(forget_aired(cursors.get_mut(TV_CHANNEL)));
+        assert_eq!(start_over(&mut rotation, &cursors), ["A S1E1", "A S1E2", "B S1E1"]);
+        // Nothing left to forget, so a playlist that still plays nothing stops
+        assert!(!forget_aired(cursors.get_mut(TV_CHANNEL)));
+    }
+
+    #[test]
+    fn empty_items_finish_instead_of_stalling() {
+        let shows = library(&[("A", 1)]);
+        let mut rotation = rotation(&[item("Missing"), item("A")]);
+        assert_eq!(play(&mut rotation, ChannelRotation::RoundRobin, &shows, 5), ["A S1E1"]);
+        assert_eq!(rotation.pick(ChannelRotation::RoundRobin), None);
+    }
+
+    #[test]
+    fn seeded_shuffles_keep_their_order_across_restarts() {
+        let shows = library(&[("A", 8)]);
+        let path = |name: &String| {
+            shows["A"].iter().find(|episode| episode.name == *name).unwrap().file_path.clone()
+        };
+        for mode in [PlayMode::Once, PlayMode::Loop] {
+            let mut shuffled = item("A");
+            shuffled.order = PlayOrder::Shuffle;
+            shuffled.seed = Some(7);
+            shuffled.mode = Some(mode);
+            let order = play(&mut rotation(&[shuffled.clone()]), ChannelRotation::Sequential, &shows, 8);
+            assert_ne!(order, play(&mut rotation(&[item("A")]), ChannelRotation::Sequential, &shows, 8));
+
+            for aired in [1, 3, 6] {
+                // Restarted after `aired` episodes: `Once` items leave out what
+                // has aired, the others resume at the interrupted episode
+                let mut restarted = rotation(&[shuffled.clone()]);
+                let mut expected = order[aired..].to_vec();
+                if mode == PlayMode::Once {
+                    let broken = HashSet::from([path(&order[aired])]);
+                    let aired = order[..aired].iter().map(path).collect();
+                    restarted.load_passes(&shows, &HashMap::new(), &broken, &aired);
+                    expected.remove(0);
+                } else {
+                    restarted.load_passes(&shows, &HashMap::new(), &HashSet::new(), &HashSet::new());
+                    assert_eq!(restarted.resume_at(&path(&order[aired])), Some(0));
+                    expected.extend_from_slice(&order);
+                }
+                let played = play(&mut restarted, ChannelRotation::Sequential, &shows, expected.len());
+                assert_eq!(played, expected, "{:?} after {} aired", mode, aired);
+            }
+        }
+    }
+
+    #[test]
+    fn resumes_at_an_episode_of_the_current_pass() {
+        let shows = library(&[("A", 4), ("B", 2)]);
+        let mut rotation = rotation(&[item("A"), item("B")]);
+        rotation.load_passes(&shows, &HashMap::new(), &HashSet::new(), &HashSet::new());
+
+        let resumed = rotation.resume_at(Path::new("/library/A/A S1E3.mkv"));
+        assert_eq!(resumed, Some(0));
+        assert_eq!(play(&mut rotation, ChannelRotation::Sequential, &shows, 2), ["A S1E3", "A S1E4"]);
+        assert_eq!(rotation.resume_at(Path::new("/library/A/A S1E1.mkv")), None);
+    }
+}
@@ -0,0 +1,134 @@
+use std::path::{ Path, PathBuf };
+
+use anyhow::{ Context, Result };
+use tokio::{ io::AsyncWriteExt, sync::Mutex };
+
+use crate::library::migrate_legacy_config;
+use crate::models::{ AppConfig, AppState, Cursors };
+
+fn exe_dir() -> Result<PathBuf> {
+    let exe_dir = std::env
+        ::current_exe()
+        .context("Failed to get executable path")?
+        .parent()
+        .context("Failed to get executable directory")?
+        .to_path_buf();
+
+    Ok(exe_dir)
+}
+
+fn config_path() -> Result<PathBuf> {
+    Ok(exe_dir()?.join("config.yml"))
+}
+
+/// Serializes config saves. Held while the state is read too, so a save
+/// never overwrites a newer snapshot with an older one.
+static CONFIG_SAVE: Mutex<()> = Mutex::const_new(());
+
+/// Serializes cursor saves, which share one temporary file
+static CURSORS_SAVE: Mutex<()> = Mutex::const_new(());
+
+/// Cursors change with every episode, so they live apart from the config
+fn cursors_path() -> Result<PathBuf> {
+    Ok(exe_dir()?.join("cursors.yml"))
+}
+
+pub async fn load_config() -> Result<AppConfig> {
+    let config_path = config_path()?;
+
+    if !config_path.exists() {
+        println!("No config.yml found at {}, using default configuration", config_path.display());
+        return Ok(AppConfig::default());
+    }
+
+    println!("Loading configuration from {}", config_path.display());
+    let content = tokio::fs
+        ::read_to_string(&config_path).await
+        .context("Failed to read config.yml")?;
+
+    let mut config: AppConfig = serde_yaml
+        ::from_str(&content)
+        .context("Failed to parse config.yml")?;
+    migrate_legacy_config(&mut config);
+
+    println!("Configuration loaded successfully");
+    Ok(config)
+}
+
+pub async fn save_config(state: &AppState) -> Result<(), String> {
+    let _guard = CONFIG_SAVE.lock().await;
+    let config_path = config_path().map_err(|e| e.to_string())?;
+
+    let config = AppConfig {
+        videos_folder: None,
+        libraries: state.libraries.read().await.clone(),
+        shows: state.shows.read().await.clone(),
+        playlist: state.playlist.read().await.clone(),
+        active_playlist: state.active_playlist.read().await.clone(),
+        playlists: state.playlists.read().await.clone(),
+        played_episodes: Default::default(),
+        subtitle_mode: state.subtitle_mode.read().await.clone(),
+        playlist_end: *state.playlist_end.read().await,
+        channel_rotation: *state.channel_rotation.read().await,
+        watch_library: *state.watch_library.read().await,
+        parse_rules: state.parse_rules.read().await.clone(),
+        scan_settings: state.scan_settings.read().await.clone(),
+        overrides: state.overrides.read().await.clone(),
+        show_metadata: state.show_metadata.read().await.clone(),
+        file_health: state.file_health.read().await.clone(),
+    };
+
+    let yaml = serde_yaml
+        ::to_string(&config)
+        .map_err(|e| format!("Failed to serialize config: {}", e))?;
+
+    write_atomically(&config_path, &yaml).await?;
+
+    println!("Configuration saved to {}", config_path.display());
+    Ok(())
+}
+
+/// `None` if there is no cursors file yet
+pub async fn load_cursors() -> Result<Option<Cursors>> {
+    let path = cursors_path()?;
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    let content = tokio::fs
+        ::read_to_string(&path).await
+        .context("Failed to read cursors.yml")?;
+    let cursors = serde_yaml::from_str(&content).context("Failed to parse cursors.yml")?;
+    Ok(Some(cursors))
+}
+
+pub async fn save_cursors(state: &AppState) -> Result<(), String> {
+    let _guard = CURSORS_SAVE.lock().await;
+    let path = cursors_path().map_err(|e| e.to_string())?;
+
+    let yaml = serde_yaml
+        ::to_string(&*state.cursors.read().await)
+        .map_err(|e| format!("Failed to serialize cursors: {}", e))?;
+
+    write_atomically(&path, &yaml).await
+}
+
+/// Write to a temporary file, flush it to disk and move it into place, so a
+/// crash never leaves a half-written file behind. Callers hold the lock for
+/// `path`, since the temporary file is shared.
+async fn write_atomically(path: &Path, contents: &str) -> Result<(), String> {
+    let tmp_path = path.with_extension("yml.tmp");
+
+    let mut file = tokio::fs::File
+        ::create(&tmp_path).await
+        .map_err(|e| format!("Failed to create {}: {}", tmp_path.display(), e))?;
+    file
+        .write_all(contents.as_bytes()).await
+        .map_err(|e| format!("Failed to write {}: {}", tmp_path.display(), e))?;
+    file.sync_all().await.map_err(|e| format!("Failed to flush {}: {}", tmp_path.display(), e))?;
+    drop(file);
+
+    tokio::fs
+        ::rename(&tmp_path, &path).await
+        .map_err(|e| format!("Failed to replace {}: {}", path.display(), e))
+}
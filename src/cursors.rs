@@ -0,0 +1,386 @@
+use std::{
+    collections::{ hash_map::Entry, BTreeSet, HashMap, HashSet },
+    path::PathBuf,
+    time::{ SystemTime, UNIX_EPOCH },
+};
+
+use crate::config::save_cursors;
+use crate::models::{ AppState, Cursors, Episode, ShowCursor };
+
+/// The only channel so far
+pub const TV_CHANNEL: &str = "tv";
+
+/// Index of the next episode to air in a show's episode list
+pub fn next_position(cursor: &ShowCursor, episodes: &[Episode]) -> usize {
+    let position = episodes
+        .iter()
+        .position(|episode| !cursor.file_id.is_empty() && episode.file_id == cursor.file_id)
+        .unwrap_or(cursor.position);
+    (position + usize::from(cursor.finished)).min(episodes.len())
+}
+
+/// Every file a channel has aired
+pub fn aired_files(
+    cursors: Option<&HashMap<String, ShowCursor>>,
+    shows: &HashMap<String, Vec<Episode>>
+) -> HashSet<PathBuf> {
+    let aired: HashSet<&str> = cursors
+        .into_iter()
+        .flatten()
+        .flat_map(|(_, cursor)| cursor.aired.iter().flatten())
+        .map(String::as_str)
+        .collect();
+    shows
+        .values()
+        .flatten()
+        .filter(|episode| !episode.file_id.is_empty() && aired.contains(episode.file_id.as_str()))
+        .map(|episode| episode.file_path.clone())
+        .collect()
+}
+
+/// File ids of the episodes before `next`
+fn aired_before(episodes: &[Episode], next: usize) -> BTreeSet<String> {
+    episodes[..next.min(episodes.len())]
+        .iter()
+        .filter(|episode| !episode.file_id.is_empty())
+        .map(|episode| episode.file_id.clone())
+        .collect()
+}
+
+/// Point a show's cursor so that `episodes[next]` airs next, counting the
+/// episodes before it as aired. Moving it back to the first episode forgets
+/// the show.
+pub fn set_next_position(
+    cursors: &mut HashMap<String, ShowCursor>,
+    show: &str,
+    episodes: &[Episode],
+    next: usize
+) {
+    let next = next.min(episodes.len());
+    if next == 0 {
+        cursors.remove(show);
+        return;
+    }
+
+    let episode = &episodes[next - 1];
+    cursors.insert(show.to_string(), ShowCursor {
+        file_id: episode.file_id.clone(),
+        position: next - 1,
+        finished: true,
+        offset: 0.0,
+        updated_at: unix_now(),
+        aired: Some(aired_before(episodes, next)),
+    });
+}
+
+/// Move the show's cursor to `episode`, which has just started (at `offset`
+/// seconds) or finished airing on `channel`, and save the cursors right away
+pub async fn record_airing(
+    state: &AppState,
+    channel: &str,
+    episode: &Episode,
+    finished: bool,
+    offset: f64
+) {
+    let position = state.shows
+        .read().await
+        .get(&episode.show_name)
+        .and_then(|episodes| episodes.iter().position(|e| e.file_path == episode.file_path));
+    let Some(position) = position else {
+        println!("[tv] {} is no longer in the library, leaving its show's cursor alone", episode.name);
+        return;
+    };
+
+    {
+        let mut cursors = state.cursors.write().await;
+        let cursor = cursors
+            .entry(channel.to_string())
+            .or_default()
+            .entry(episode.show_name.clone())
+            .or_default();
+        cursor.file_id = episode.file_id.clone();
+        cursor.position = position;
+        cursor.finished = finished;
+        cursor.offset = offset;
+        cursor.updated_at = unix_now();
+        let aired = cursor.aired.get_or_insert_default();
+        if finished && !episode.file_id.is_empty() {
+            aired.insert(episode.file_id.clone());
+        }
+    }
+
+    if let Err(e) = save_cursors(state).await {
+        eprintln!("[tv] Failed to save cursors: {}", e);
+    }
+}
+
+/// Remember how far into `episode` the channel got, so it can resume there
+pub async fn record_offset(state: &AppState, channel: &str, episode: &Episode, offset: f64) {
+    {
+        let mut cursors = state.cursors.write().await;
+        let Some(cursor) = cursors.get_mut(channel).and_then(|shows| shows.get_mut(&episode.show_name)) else {
+            return;
+        };
+        if cursor.finished || cursor.file_id != episode.file_id {
+            return;
+        }
+        cursor.offset = offset;
+        cursor.updated_at = unix_now();
+    }
+
+    if let Err(e) = save_cursors(state).await {
+        eprintln!("[tv] Failed to save cursors: {}", e);
+    }
+}
+
+/// The episode a channel was airing when it last stopped, and how far it got
+pub fn interrupted_airing(
+    cursors: Option<&HashMap<String, ShowCursor>>,
+    shows: &HashMap<String, Vec<Episode>>
+) -> Option<(Episode, f64)> {
+    let (show, cursor) = cursors
+        .into_iter()
+        .flatten()
+        .filter(|(_, cursor)| !cursor.finished)
+        .max_by_key(|(_, cursor)| cursor.updated_at)?;
+    let episodes = shows.get(show)?;
+    // Files that were missing when an older config was migrated have no id
+    let episode = if cursor.file_id.is_empty() {
+        episodes.get(cursor.position)?
+    } else {
+        episodes.iter().find(|episode| episode.file_id == cursor.file_id)?
+    };
+    Some((episode.clone(), cursor.offset))
+}
+
+/// Move the cursors of shows that were renamed or merged into another show
+/// (through overrides or parse rules) over to the new name, found through
+/// the episodes they point at. Returns whether any cursor moved.
+fn follow_renames(cursors: &mut Cursors, shows: &HashMap<String, Vec<Episode>>) -> bool {
+    let show_of: HashMap<&str, &str> = shows
+        .iter()
+        .flat_map(|(name, episodes)| {
+            episodes
+                .iter()
+                .filter(|episode| !episode.file_id.is_empty())
+                .map(move |episode| (episode.file_id.as_str(), name.as_str()))
+        })
+        .collect();
+
+    let mut moved = false;
+    for show_cursors in cursors.values_mut() {
+        let orphaned: Vec<String> = show_cursors
+            .keys()
+            .filter(|show| !shows.contains_key(*show))
+            .cloned()
+            .collect();
+        for old_name in orphaned {
+            let cursor = &show_cursors[&old_name];
+            // A show that is gone entirely keeps its cursor in case it comes back
+            let Some(new_name) = std::iter
+                ::once(&cursor.file_id)
+                .chain(cursor.aired.iter().flatten())
+                .find_map(|file_id| show_of.get(file_id.as_str()))
+            else {
+                continue;
+            };
+            let new_name = new_name.to_string();
+            let Some(cursor) = show_cursors.remove(&old_name) else {
+                continue;
+            };
+            println!("[tv] Moved the cursor of '{}' to '{}'", old_name, new_name);
+            moved = true;
+
+            match show_cursors.entry(new_name) {
+                Entry::Vacant(entry) => {
+                    entry.insert(cursor);
+                }
+                // Merged into a show with its own cursor: keep the later one
+                // and everything either has aired
+                Entry::Occupied(mut entry) => {
+                    let existing = entry.get_mut();
+                    let mut aired = existing.aired.take().unwrap_or_default();
+                    aired.extend(cursor.aired.iter().flatten().cloned());
+                    if cursor.updated_at > existing.updated_at {
+                        *existing = cursor;
+                    }
+                    existing.aired = Some(aired);
+                }
+            }
+        }
+    }
+    moved
+}
+
+/// Forget aired files that are no longer in the library, deleted or replaced
+/// by a different file, so no aired set outgrows its show. Returns whether
+/// anything was forgotten.
+pub fn prune_aired(cursors: &mut Cursors, shows: &HashMap<String, Vec<Episode>>) -> bool {
+    let known: HashSet<&str> = shows
+        .values()
+        .flatten()
+        .map(|episode| episode.file_id.as_str())
+        .filter(|file_id| !file_id.is_empty())
+        .collect();
+
+    let mut pruned = false;
+    for cursor in cursors.values_mut().flat_map(HashMap::values_mut) {
+        if let Some(aired) = &mut cursor.aired {
+            let before = aired.len();
+            aired.retain(|file_id| known.contains(file_id.as_str()));
+            pruned |= aired.len() != before;
+        }
+    }
+    pruned
+}
+
+/// Bring the cursors of `state` in line with its updated `shows`: move those
+/// of renamed shows and prune what aired. Saves the cursors if they changed.
+pub async fn update_cursors(state: &AppState, shows: &HashMap<String, Vec<Episode>>) {
+    let changed = {
+        let mut cursors = state.cursors.write().await;
+        let moved = follow_renames(&mut cursors, shows);
+        prune_aired(&mut cursors, shows) || moved
+    };
+    if changed && let Err(e) = save_cursors(state).await {
+        eprintln!("[tv] Failed to save cursors: {}", e);
+    }
+}
+
+/// Forget everything a channel has aired, so its `Once` items play again when
+/// the playlist starts over. The cursors themselves stay. Returns whether
+/// anything had aired.
+pub fn forget_aired(cursors: Option<&mut HashMap<String, ShowCursor>>) -> bool {
+    let mut forgot = false;
+    for cursor in cursors.into_iter().flat_map(HashMap::values_mut) {
+        if let Some(aired) = &mut cursor.aired {
+            forgot |= !aired.is_empty();
+            aired.clear();
+        }
+    }
+    forgot
+}
+
+/// Cursor files from before aired sets were kept only had the cursor, so
+/// take the episodes before it as aired, as they used to be. Returns whether
+/// any cursor was filled in.
+pub fn fill_legacy_aired(cursors: &mut Cursors, shows: &HashMap<String, Vec<Episode>>) -> bool {
+    let mut filled = false;
+    for (show, cursor) in cursors.values_mut().flatten() {
+        if cursor.aired.is_some() {
+            continue;
+        }
+        let episodes = shows.get(show).map_or(&[][..], Vec::as_slice);
+        cursor.aired = Some(aired_before(episodes, next_position(cursor, episodes)));
+        filled = true;
+    }
+    filled
+}
+
+/// Turn the played-episode lists of older configs into cursors at the
+/// latest played episode of each show
+pub fn migrate_played_episodes(
+    played: &HashMap<String, Vec<usize>>,
+    shows: &HashMap<String, Vec<Episode>>
+) -> Cursors {
+    let mut cursors = HashMap::new();
+    for (show, ids) in played {
+        let ids: HashSet<usize> = ids.iter().copied().collect();
+        let Some(episodes) = shows.get(show) else {
+            continue;
+        };
+        if let Some(last) = episodes.iter().rposition(|episode| ids.contains(&episode.id)) {
+            set_next_position(&mut cursors, show, episodes, last + 1);
+        }
+        // The lists were exact, unlike the cursor
+        if let Some(cursor) = cursors.get_mut(show) {
+            cursor.aired = Some(
+                episodes
+                    .iter()
+                    .filter(|episode| ids.contains(&episode.id) && !episode.file_id.is_empty())
+                    .map(|episode| episode.file_id.clone())
+                    .collect()
+            );
+        }
+    }
+
+    if cursors.is_empty() {
+        return Cursors::new();
+    }
+    println!("[tv] Migrated played episodes of {} show(s) to cursors", cursors.len());
+    Cursors::from([(TV_CHANNEL.to_string(), cursors)])
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::library::migrate_legacy_config;
+    use crate::models::fixtures::library;
+    use crate::models::AppConfig;
+
+    #[test]
+    fn prunes_aired_files_that_left_the_library() {
+        let mut shows = library(&[("A", 3)]);
+        let mut cursors = Cursors::new();
+        let channel = cursors.entry(TV_CHANNEL.to_string()).or_default();
+        set_next_position(channel, "A", &shows["A"], 3);
+        assert!(!prune_aired(&mut cursors, &shows));
+
+        // Replaced by a file of another size, so with another id
+        shows.get_mut("A").unwrap()[1].file_id = "replaced".to_string();
+        assert!(prune_aired(&mut cursors, &shows));
+        let aired = aired_files(cursors.get(TV_CHANNEL), &shows);
+        let expected = HashSet::from([shows["A"][0].file_path.clone(), shows["A"][2].file_path.clone()]);
+        assert_eq!(aired, expected);
+        assert_eq!(cursors[TV_CHANNEL]["A"].aired.as_ref().map(BTreeSet::len), Some(2));
+    }
+
+    #[test]
+    fn keeps_played_episodes_of_configs_without_file_ids() {
+        let folder = std::env::temp_dir().join(format!("rurushi-legacy-{}", std::process::id()));
+        std::fs::create_dir_all(&folder).unwrap();
+        let mut shows = String::new();
+        for number in 1..=4 {
+            let path = folder.join(format!("Show - 0{}.mkv", number));
+            std::fs::write(&path, vec![0; number]).unwrap();
+            shows += &format!("  - id: {}\n    name: Show - 0{}\n", number - 1, number);
+            shows += &format!("    file_path: {}\n    show_name: Show\n", path.display());
+            shows += &format!("    episode_number: {}\n", number);
+        }
+        // Shaped like config.yml before libraries, fingerprints and cursors
+        let yaml = format!(
+            "videos_folder: {}\nshows:\n  Show:\n{}playlist: []\n{}",
+            folder.display(),
+            shows,
+            "played_episodes:\n  Show: [0, 2]\nsubtitle_mode: None\n"
+        );
+        let mut config: AppConfig = serde_yaml::from_str(&yaml).unwrap();
+        migrate_legacy_config(&mut config);
+        std::fs::remove_dir_all(&folder).unwrap();
+
+        let cursors = migrate_played_episodes(&config.played_episodes, &config.shows);
+        let episodes = &config.shows["Show"];
+        let aired = aired_files(cursors.get(TV_CHANNEL), &config.shows);
+        let expected = HashSet::from([episodes[0].file_path.clone(), episodes[2].file_path.clone()]);
+        assert_eq!(aired, expected);
+
+        let mut cursors = cursors;
+        let cursor = cursors.get_mut(TV_CHANNEL).unwrap().get_mut("Show").unwrap();
+        assert!(!cursor.file_id.is_empty());
+        assert_eq!(next_position(cursor, episodes), 3);
+
+        // Stopped halfway through the latest episode instead
+        cursor.finished = false;
+        cursor.offset = 60.0;
+        let (resumed, offset) = interrupted_airing(cursors.get(TV_CHANNEL), &config.shows).unwrap();
+        assert_eq!(resumed.file_path, episodes[2].file_path);
+        assert_eq!(offset, 60.0);
+    }
+}
@@ -1,11 +1,11 @@
 use std::{ sync::Arc, time::Duration };
 use axum::{
     extract::{ Path as AxPath, State },
-    http::{ StatusCode, Uri, HeaderMap },
+    http::{ header, StatusCode, Uri, HeaderMap },
     response::{ IntoResponse, Redirect, Response },
 };
 
-use crate::models::AppState;
+use crate::models::{ AppState, MediaMetadata };
 use crate::streaming::{ start_tv_loop_if_needed, wait_for_file };
 
 pub async fn stream_m3u8(
@@ -40,6 +40,59 @@ pub async fn stream_m3u8(
     Ok(Redirect::temporary(&redirect).into_response())
 }
 
+/// GET /api/artwork/shows/{name}/{kind}
+pub async fn show_artwork(
+    State(state): State<Arc<AppState>>,
+    AxPath((name, kind)): AxPath<(String, String)>
+) -> Result<Response, (StatusCode, String)> {
+    let metadata = state.show_metadata.read().await.get(&name).cloned();
+    serve_artwork(metadata, &kind).await
+}
+
+/// GET /api/artwork/episodes/{file_id}/{kind}
+pub async fn episode_artwork(
+    State(state): State<Arc<AppState>>,
+    AxPath((file_id, kind)): AxPath<(String, String)>
+) -> Result<Response, (StatusCode, String)> {
+    let metadata = state.shows
+        .read().await
+        .values()
+        .flatten()
+        .find(|episode| episode.file_id == file_id)
+        .and_then(|episode| episode.metadata.clone());
+    serve_artwork(metadata, &kind).await
+}
+
+/// Only images recorded during a scan are served, never arbitrary paths
+async fn serve_artwork(
+    metadata: Option<MediaMetadata>,
+    kind: &str
+) -> Result<Response, (StatusCode, String)> {
+    let metadata = metadata.unwrap_or_default();
+    let path = match kind {
+        "poster" | "thumb" => metadata.poster,
+        "fanart" => metadata.fanart,
+        _ => {
+            return Err((StatusCode::BAD_REQUEST, format!("Unknown artwork kind: {}", kind)));
+        }
+    };
+    let Some(path) = path else {
+        return Err((StatusCode::NOT_FOUND, "No artwork available".into()));
+    };
+
+    let bytes = tokio::fs
+        ::read(&path).await
+        .map_err(|e| (StatusCode::NOT_FOUND, format!("Failed to read artwork: {}", e)))?;
+
+    let content_type = match path.extension().and_then(|ext| ext.to_str()) {
+        Some("png") => "image/png",
+        Some("webp") => "image/webp",
+        _ => "image/jpeg",
+    };
+
+    Ok(([(header::CONTENT_TYPE, content_type)], bytes).into_response())
+}
+
 pub async fn health_check() -> impl IntoResponse {
     (StatusCode::OK, "OK")
 }
@@ -0,0 +1,504 @@
+use std::{
+    collections::{ HashMap, HashSet },
+    path::{ Path, PathBuf },
+    process::Output,
+    sync::{ atomic::{ AtomicBool, AtomicU64, AtomicUsize, Ordering }, Arc, Mutex },
+    time::{ Duration, Instant, SystemTime, UNIX_EPOCH },
+};
+
+use axum::http::StatusCode;
+use serde::Serialize;
+use serde_json::Value;
+use tokio::{ process::Command, sync::Semaphore, task::JoinSet, time };
+
+use crate::config::save_config;
+use crate::models::{ AppState, Episode, FileHealth };
+use crate::probe::ffprobe_available;
+use crate::scan::ScanJobState;
+use crate::streaming::check_ffmpeg_availability;
+
+/// Seconds decoded at each sample point
+const SAMPLE_SECONDS: f64 = 5.0;
+const PROBE_TIMEOUT: Duration = Duration::from_secs(30);
+const DECODE_TIMEOUT: Duration = Duration::from_secs(60);
+/// How often the scheduler looks at `verify_interval_hours`
+const SCHEDULER_TICK: Duration = Duration::from_secs(60);
+/// Lines of ffmpeg/ffprobe stderr kept per error
+const MAX_ERROR_LINES: usize = 3;
+
+static NEXT_JOB_ID: AtomicU64 = AtomicU64::new(1);
+
+pub struct HealthCheckOptions {
+    /// Only check files from this library root
+    pub library: Option<String>,
+    /// Decode sample segments in addition to probing
+    pub decode: bool,
+    /// Re-check files that already have a current result
+    pub full: bool,
+    /// Also re-check results older than this many seconds
+    pub max_age: Option<u64>,
+}
+
+struct HealthOutcome {
+    state: ScanJobState,
+    elapsed: Option<Duration>,
+    error: Option<String>,
+}
+
+pub struct HealthJob {
+    pub id: String,
+    decode: bool,
+    started: Instant,
+    total: usize,
+    checked: AtomicUsize,
+    broken: AtomicUsize,
+    cancelled: AtomicBool,
+    outcome: Mutex<HealthOutcome>,
+}
+
+#[derive(Serialize)]
+pub struct HealthJobStatus {
+    pub id: String,
+    pub state: ScanJobState,
+    pub decode: bool,
+    pub total: usize,
+    pub checked: usize,
+    pub broken: usize,
+    pub elapsed_ms: u128,
+    pub error: Option<String>,
+}
+
+impl HealthJob {
+    fn new(total: usize, decode: bool) -> Self {
+        Self {
+            id: format!("health-{}", NEXT_JOB_ID.fetch_add(1, Ordering::Relaxed)),
+            decode,
+            started: Instant::now(),
+            total,
+            checked: AtomicUsize::new(0),
+            broken: AtomicUsize::new(0),
+            cancelled: AtomicBool::new(false),
+            outcome: Mutex::new(HealthOutcome {
+                state: ScanJobState::Running,
+                elapsed: None,
+                error: None,
+            }),
+        }
+    }
+
+    pub fn state(&self) -> ScanJobState {
+        self.outcome.lock().unwrap().state
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    fn finish(&self, state: ScanJobState, error: Option<String>) {
+        let mut outcome = self.outcome.lock().unwrap();
+        outcome.state = state;
+        outcome.elapsed = Some(self.started.elapsed());
+        outcome.error = error;
+    }
+
+    pub fn status(&self) -> HealthJobStatus {
+        let outcome = self.outcome.lock().unwrap();
+        HealthJobStatus {
+            id: self.id.clone(),
+            state: outcome.state,
+            decode: self.decode,
+            total: self.total,
+            checked: self.checked.load(Ordering::Relaxed),
+            broken: self.broken.load(Ordering::Relaxed),
+            elapsed_ms: outcome.elapsed.unwrap_or_else(|| self.started.elapsed()).as_millis(),
+            error: outcome.error.clone(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct BrokenFile {
+    pub file_id: String,
+    pub show_name: String,
+    pub name: String,
+    pub path: String,
+    pub errors: Vec<String>,
+    pub decoded: bool,
+    pub checked_at: u64,
+}
+
+#[derive(Serialize)]
+pub struct HealthReport {
+    pub job: Option<HealthJobStatus>,
+    /// Files currently available on disk
+    pub total: usize,
+    pub healthy: usize,
+    pub broken: usize,
+    /// Never checked, or changed since the last check
+    pub unchecked: usize,
+    pub broken_files: Vec<BrokenFile>,
+}
+
+/// Start a background integrity check. Only one check runs at a time.
+pub async fn start_health_check(
+    state: Arc<AppState>,
+    options: HealthCheckOptions
+) -> Result<Arc<HealthJob>, (StatusCode, String)> {
+    let mut current = state.health_job.write().await;
+    if let Some(job) = current.as_ref() && job.state() == ScanJobState::Running {
+        return Err((StatusCode::CONFLICT, format!("Integrity check {} is already running", job.id)));
+    }
+
+    if
+        let Some(id) = &options.library &&
+        !state.libraries.read().await.iter().any(|root| &root.id == id)
+    {
+        return Err((StatusCode::NOT_FOUND, format!("Library '{}' not found", id)));
+    }
+
+    if !ffprobe_available().await {
+        return Err((StatusCode::SERVICE_UNAVAILABLE, "ffprobe not found".to_string()));
+    }
+    if options.decode {
+        check_ffmpeg_availability().await.map_err(|e| (StatusCode::SERVICE_UNAVAILABLE, e))?;
+    }
+
+    let targets = {
+        let shows = state.shows.read().await;
+        let health = state.file_health.read().await;
+        let now = unix_now();
+        shows
+            .values()
+            .flatten()
+            .filter(|episode| episode.available && !episode.file_id.is_empty())
+            .filter(|episode| options.library.as_ref().is_none_or(|id| &episode.library_id == id))
+            .filter(|episode| {
+                let Some(result) = health.get(&episode.file_id) else {
+                    return true;
+                };
+                options.full ||
+                    !result.is_current(episode) ||
+                    options.max_age.is_some_and(|age| now.saturating_sub(result.checked_at) >= age)
+            })
+            .cloned()
+            .collect::<Vec<Episode>>()
+    };
+
+    let job = Arc::new(HealthJob::new(targets.len(), options.decode));
+    *current = Some(Arc::clone(&job));
+    drop(current);
+
+    println!(
+        "[health] Started integrity check {} for {} file(s){}",
+        job.id,
+        targets.len(),
+        if options.decode { " with decoding" } else { "" }
+    );
+
+    let job_clone = Arc::clone(&job);
+    tokio::spawn(async move {
+        run_health_check(state, job_clone, targets).await;
+    });
+
+    Ok(job)
+}
+
+async fn run_health_check(state: Arc<AppState>, job: Arc<HealthJob>, targets: Vec<Episode>) {
+    let workers = state.scan_settings.read().await.probe_workers.max(1);
+    let semaphore = Arc::new(Semaphore::new(workers));
+    let mut tasks = JoinSet::new();
+    let decode = job.decode;
+
+    for episode in targets {
+        let Ok(permit) = Arc::clone(&semaphore).acquire_owned().await else {
+            break;
+        };
+        if job.cancelled.load(Ordering::Relaxed) {
+            break;
+        }
+
+        // Record results as they come in so the TV loop can skip bad files early
+        while let Some(result) = tasks.try_join_next() {
+            record_result(&state, &job, result).await;
+        }
+
+        tasks.spawn(async move {
+            let _permit = permit;
+            let errors = check_file(&episode.file_path, decode).await;
+            (episode, errors)
+        });
+    }
+
+    while let Some(result) = tasks.join_next().await {
+        record_result(&state, &job, result).await;
+    }
+
+    {
+        let shows = state.shows.read().await;
+        let known: HashSet<&str> = shows
+            .values()
+            .flatten()
+            .map(|episode| episode.file_id.as_str())
+            .collect();
+        state.file_health.write().await.retain(|file_id, _| known.contains(file_id.as_str()));
+    }
+
+    let status = job.status();
+    let cancelled = job.cancelled.load(Ordering::Relaxed);
+    println!(
+        "[health] Integrity check {} {}: {} of {} file(s) checked, {} broken",
+        job.id,
+        if cancelled { "cancelled" } else { "finished" },
+        status.checked,
+        status.total,
+        status.broken
+    );
+
+    match save_config(&state).await {
+        Ok(()) if cancelled => job.finish(ScanJobState::Cancelled, None),
+        Ok(()) => job.finish(ScanJobState::Completed, None),
+        Err(e) => job.finish(ScanJobState::Failed, Some(format!("Failed to save config: {}", e))),
+    }
+}
+
+async fn record_result(
+    state: &AppState,
+    job: &HealthJob,
+    result: Result<(Episode, Vec<String>), tokio::task::JoinError>
+) {
+    let (episode, errors) = match result {
+        Ok(result) => result,
+        Err(e) => {
+            eprintln!("[health] Check task failed: {}", e);
+            return;
+        }
+    };
+
+    job.checked.fetch_add(1, Ordering::Relaxed);
+    if !errors.is_empty() {
+        job.broken.fetch_add(1, Ordering::Relaxed);
+        eprintln!("[health] {}: {}", episode.file_path.display(), errors.join("; "));
+    }
+
+    state.file_health.write().await.insert(episode.file_id.clone(), FileHealth {
+        errors,
+        decoded: job.decode,
+        checked_at: unix_now(),
+        file_size: episode.file_size,
+        modified: episode.modified,
+    });
+}
+
+/// Probe the file, then optionally decode a few seconds at the start, middle
+/// and end. Returns the problems found.
+async fn check_file(path: &Path, decode: bool) -> Vec<String> {
+    let (duration, mut errors) = match probe_for_errors(path).await {
+        Ok(result) => result,
+        Err(e) => {
+            return vec![e];
+        }
+    };
+
+    if decode {
+        for offset in sample_offsets(duration) {
+            if let Err(e) = decode_sample(path, offset).await {
+                errors.push(format!("Decoding at {:.0}s: {}", offset, e));
+            }
+        }
+    }
+
+    errors
+}
+
+async fn probe_for_errors(path: &Path) -> Result<(Option<f64>, Vec<String>), String> {
+    let mut cmd = Command::new("ffprobe");
+    cmd.args(["-v", "error"])
+        .args(["-print_format", "json"])
+        .args(["-show_entries", "format=duration:stream=codec_type"])
+        .arg(path.as_os_str());
+    let output = run_with_timeout(cmd, PROBE_TIMEOUT, "ffprobe").await?;
+
+    if !output.status.success() {
+        return Err(
+            stderr_summary(&output).unwrap_or_else(|| format!("ffprobe exited with {}", output.status))
+        );
+    }
+
+    let json: Value = serde_json
+        ::from_slice(&output.stdout)
+        .map_err(|e| format!("Failed to parse ffprobe output: {}", e))?;
+
+    let mut errors: Vec<String> = stderr_summary(&output).into_iter().collect();
+
+    let has_media = json["streams"]
+        .as_array()
+        .is_some_and(|streams| {
+            streams.iter().any(|s| s["codec_type"] == "video" || s["codec_type"] == "audio")
+        });
+    if !has_media {
+        errors.push("No audio or video streams".to_string());
+    }
+
+    let duration = json["format"]["duration"]
+        .as_str()
+        .and_then(|d| d.parse::<f64>().ok())
+        .filter(|d| *d > 0.0);
+    if duration.is_none() {
+        errors.push("Unknown duration".to_string());
+    }
+
+    Ok((duration, errors))
+}
+
+fn sample_offsets(duration: Option<f64>) -> Vec<f64> {
+    match duration {
+        Some(duration) if duration > SAMPLE_SECONDS * 3.0 => {
+            vec![0.0, duration / 2.0, duration - SAMPLE_SECONDS]
+        }
+        _ => vec![0.0],
+    }
+}
+
+async fn decode_sample(path: &Path, offset: f64) -> Result<(), String> {
+    let mut cmd = Command::new("ffmpeg");
+    cmd.args(["-v", "error", "-nostdin"])
+        .args(["-ss", &format!("{:.3}", offset)])
+        .arg("-i")
+        .arg(path.as_os_str())
+        .args(["-t", &SAMPLE_SECONDS.to_string()])
+        .args(["-f", "null", "-"]);
+    let output = run_with_timeout(cmd, DECODE_TIMEOUT, "ffmpeg").await?;
+
+    match stderr_summary(&output) {
+        Some(errors) => Err(errors),
+        None if !output.status.success() => Err(format!("ffmpeg exited with {}", output.status)),
+        None => Ok(()),
+    }
+}
+
+async fn run_with_timeout(mut cmd: Command, timeout: Duration, name: &str) -> Result<Output, String> {
+    let output = cmd.kill_on_drop(true).output();
+    time
+        ::timeout(timeout, output).await
+        .map_err(|_| format!("{} timed out", name))?
+        .map_err(|e| format!("Failed to run {}: {}", name, e))
+}
+
+fn stderr_summary(output: &Output) -> Option<String> {
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let lines: Vec<&str> = stderr
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .collect();
+    if lines.is_empty() {
+        return None;
+    }
+
+    let mut summary = lines[..lines.len().min(MAX_ERROR_LINES)].join("; ");
+    if lines.len() > MAX_ERROR_LINES {
+        summary.push_str(&format!(" (+{} more)", lines.len() - MAX_ERROR_LINES));
+    }
+    Some(summary)
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Paths of files whose last check found problems and that have not changed since
+pub fn broken_files(
+    shows: &HashMap<String, Vec<Episode>>,
+    health: &HashMap<String, FileHealth>
+) -> HashSet<PathBuf> {
+    shows
+        .values()
+        .flatten()
+        .filter(|episode| {
+            health
+                .get(&episode.file_id)
+                .is_some_and(|result| result.is_broken() && result.is_current(episode))
+        })
+        .map(|episode| episode.file_path.clone())
+        .collect()
+}
+
+pub async fn health_report(state: &AppState) -> HealthReport {
+    let job = state.health_job
+        .read().await
+        .as_ref()
+        .map(|job| job.status());
+    let shows = state.shows.read().await;
+    let health = state.file_health.read().await;
+
+    let mut report = HealthReport {
+        job,
+        total: 0,
+        healthy: 0,
+        broken: 0,
+        unchecked: 0,
+        broken_files: Vec::new(),
+    };
+
+    for episode in shows.values().flatten().filter(|episode| episode.available) {
+        report.total += 1;
+        match health.get(&episode.file_id).filter(|result| result.is_current(episode)) {
+            None => {
+                report.unchecked += 1;
+            }
+            Some(result) if result.is_broken() => {
+                report.broken += 1;
+                report.broken_files.push(BrokenFile {
+                    file_id: episode.file_id.clone(),
+                    show_name: episode.show_name.clone(),
+                    name: episode.name.clone(),
+                    path: episode.file_path.display().to_string(),
+                    errors: result.errors.clone(),
+                    decoded: result.decoded,
+                    checked_at: result.checked_at,
+                });
+            }
+            Some(_) => {
+                report.healthy += 1;
+            }
+        }
+    }
+
+    report.broken_files.sort_by(|a, b| a.show_name.cmp(&b.show_name).then(a.name.cmp(&b.name)));
+    report
+}
+
+/// Run an integrity check every `verify_interval_hours`, re-checking new and
+/// changed files plus results older than the interval
+pub fn spawn_health_scheduler(state: Arc<AppState>) {
+    tokio::spawn(async move {
+        let mut last_run = Instant::now();
+        loop {
+            time::sleep(SCHEDULER_TICK).await;
+
+            let settings = state.scan_settings.read().await.clone();
+            if settings.verify_interval_hours == 0 {
+                continue;
+            }
+            let interval = Duration::from_secs(settings.verify_interval_hours * 3600);
+            if last_run.elapsed() < interval {
+                continue;
+            }
+            last_run = Instant::now();
+
+            println!("[health] Starting scheduled integrity check");
+            let options = HealthCheckOptions {
+                library: None,
+                decode: settings.verify_decode,
+                full: false,
+                max_age: Some(interval.as_secs()),
+            };
+            if let Err((_, e)) = start_health_check(Arc::clone(&state), options).await {
+                eprintln!("[health] Scheduled integrity check not started: {}", e);
+            }
+        }
+    });
+}
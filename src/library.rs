@@ -0,0 +1,197 @@
+use std::{ path::Path, sync::LazyLock };
+
+use globset::{ Glob, GlobSet, GlobSetBuilder };
+use ignore::gitignore::{ Gitignore, GitignoreBuilder };
+use regex::Regex;
+
+use crate::models::{ AppConfig, LibraryKind, LibraryRoot, ScanSettings, ShowNaming };
+use crate::video::{ fill_file_identity, migrate_episode_range };
+
+/// Derive a short unique id for a new root from its folder name
+pub fn unique_library_id(path: &Path, existing: &[LibraryRoot]) -> String {
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+
+    let mut slug = String::new();
+    for c in name.chars() {
+        if c.is_alphanumeric() {
+            slug.push(c);
+        } else if !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    let slug = slug.trim_matches('-');
+    let base = if slug.is_empty() { "library" } else { slug };
+
+    let mut id = base.to_string();
+    let mut suffix = 2;
+    while existing.iter().any(|root| root.id == id) {
+        id = format!("{}-{}", base, suffix);
+        suffix += 1;
+    }
+    id
+}
+
+pub fn new_library_root(path: &Path, existing: &[LibraryRoot]) -> LibraryRoot {
+    LibraryRoot {
+        id: unique_library_id(path, existing),
+        path: path.to_path_buf(),
+        kind: LibraryKind::default(),
+        ignore: Vec::new(),
+        naming: ShowNaming::default(),
+        enabled: true,
+    }
+}
+
+/// The most specific root containing `path`
+pub fn root_for_path<'a>(roots: &'a [LibraryRoot], path: &Path) -> Option<&'a LibraryRoot> {
+    roots
+        .iter()
+        .filter(|root| path.starts_with(&root.path))
+        .max_by_key(|root| root.path.components().count())
+}
+
+/// Bring configs from before multi-root support up to date: the single
+/// `videos_folder` becomes the first root and existing episodes are
+/// attributed to the root they live under and fingerprinted, so cursors can
+/// refer to them before the next scan. Index-based episode ranges of
+/// playlist items become episode-number selections.
+pub fn migrate_legacy_config(config: &mut AppConfig) {
+    if
+        let Some(folder) = config.videos_folder.take() &&
+        !config.libraries.iter().any(|root| root.path == folder)
+    {
+        let root = new_library_root(&folder, &config.libraries);
+        println!("[library] Migrated videos folder {} to library '{}'", folder.display(), root.id);
+        config.libraries.insert(0, root);
+    }
+
+    let mut fingerprinted = 0;
+    for episode in config.shows.values_mut().flatten() {
+        if
+            episode.library_id.is_empty() &&
+            let Some(root) = root_for_path(&config.libraries, &episode.file_path)
+        {
+            episode.library_id = root.id.clone();
+        }
+        if episode.file_id.is_empty() && fill_file_identity(episode) {
+            fingerprinted += 1;
+        }
+    }
+    if fingerprinted > 0 {
+        println!("[library] Fingerprinted {} episode(s) saved without a file id", fingerprinted);
+    }
+
+    for item in config.playlist.iter_mut().chain(config.playlists.values_mut().flatten()) {
+        migrate_episode_range(item, &config.shows, &config.show_metadata);
+    }
+}
+
+/// Gitignore-syntax file read from the top of each library root
+pub const IGNORE_FILE: &str = ".rurushiignore";
+
+static SAMPLE_FILE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)(?:^|[ ._-])sample(?:[ ._-]?\d+)?$|^sample[ ._-]").unwrap()
+});
+
+/// Everything that excludes paths below a library root: its glob patterns,
+/// its `.rurushiignore` file and the global scan settings
+pub struct IgnoreRules<'a> {
+    root: &'a Path,
+    set: GlobSet,
+    ignore_file: Option<Gitignore>,
+    settings: &'a ScanSettings,
+}
+
+impl<'a> IgnoreRules<'a> {
+    pub fn new(root: &'a LibraryRoot, settings: &'a ScanSettings) -> Self {
+        let mut builder = GlobSetBuilder::new();
+        for pattern in &root.ignore {
+            match Glob::new(pattern) {
+                Ok(glob) => {
+                    builder.add(glob);
+                }
+                Err(e) => {
+                    eprintln!("[library] Ignoring invalid pattern '{}' in '{}': {}", pattern, root.id, e);
+                }
+            }
+        }
+
+        Self {
+            root: &root.path,
+            set: builder.build().unwrap_or_else(|_| GlobSet::empty()),
+            ignore_file: read_ignore_file(&root.path),
+            settings,
+        }
+    }
+
+    pub fn is_ignored(&self, path: &Path, is_dir: bool) -> bool {
+        let Ok(relative) = path.strip_prefix(self.root) else {
+            return false;
+        };
+        if relative.as_os_str().is_empty() {
+            return false;
+        }
+
+        if self.set.is_match(relative) {
+            return true;
+        }
+
+        if
+            let Some(ignore_file) = &self.ignore_file &&
+            ignore_file.matched_path_or_any_parents(path, is_dir).is_ignore()
+        {
+            return true;
+        }
+
+        // Check every component so a walk starting deep inside an excluded folder is caught too
+        let count = relative.components().count();
+        relative
+            .components()
+            .enumerate()
+            .any(|(i, component)| {
+                let name = component.as_os_str().to_string_lossy();
+                self.is_excluded_name(&name, is_dir || i + 1 < count)
+            })
+    }
+
+    fn is_excluded_name(&self, name: &str, is_dir: bool) -> bool {
+        if !self.settings.include_hidden && name.starts_with('.') {
+            return true;
+        }
+
+        if is_dir {
+            return self.settings.excluded_folders
+                .iter()
+                .any(|folder| folder.eq_ignore_ascii_case(name));
+        }
+
+        let stem = Path::new(name)
+            .file_stem()
+            .map(|s| s.to_string_lossy())
+            .unwrap_or_default();
+        self.settings.skip_samples && SAMPLE_FILE.is_match(&stem)
+    }
+}
+
+fn read_ignore_file(root: &Path) -> Option<Gitignore> {
+    let path = root.join(IGNORE_FILE);
+    if !path.is_file() {
+        return None;
+    }
+
+    let mut builder = GitignoreBuilder::new(root);
+    if let Some(e) = builder.add(&path) {
+        eprintln!("[library] Problem reading {}: {}", path.display(), e);
+    }
+
+    match builder.build() {
+        Ok(ignore_file) => Some(ignore_file),
+        Err(e) => {
+            eprintln!("[library] Failed to load {}: {}", path.display(), e);
+            None
+        }
+    }
+}
@@ -1,73 +1,84 @@
 mod api;
+mod channel;
+mod config;
+mod cursors;
 mod handlers;
+mod health;
+mod library;
+mod metadata;
 mod models;
+mod order;
+mod parser;
+mod probe;
+mod scan;
+mod search;
+mod stats;
 mod streaming;
 mod video;
+mod watcher;
 
-use std::{ collections::HashMap, sync::Arc };
+use std::{ collections::{ HashMap, VecDeque }, sync::Arc };
 
 use anyhow::{ Context, Result };
-use axum::{ routing::{ delete, get, post }, Router };
+use axum::{ routing::{ delete, get, post, put }, Router };
 use tokio::{ fs, sync::RwLock };
 use tower_http::{ cors::CorsLayer, services::ServeDir };
 
-use models::{ AppConfig, AppState };
-
-async fn load_config() -> Result<AppConfig> {
-    let exe_dir = std::env
-        ::current_exe()
-        .context("Failed to get executable path")?
-        .parent()
-        .context("Failed to get executable directory")?
-        .to_path_buf();
-
-    let config_path = exe_dir.join("config.yml");
-
-    if !config_path.exists() {
-        println!("No config.yml found at {}, using default configuration", config_path.display());
-        return Ok(AppConfig::default());
-    }
-
-    println!("Loading configuration from {}", config_path.display());
-    let content = tokio::fs
-        ::read_to_string(&config_path).await
-        .context("Failed to read config.yml")?;
-
-    let config: AppConfig = serde_yaml::from_str(&content).context("Failed to parse config.yml")?;
-
-    println!("Configuration loaded successfully");
-    Ok(config)
-}
+use models::AppState;
 
 #[tokio::main]
 async fn main() -> Result<()> {
     let hls_root = std::env::temp_dir().join("Rurushi-hls");
     fs::create_dir_all(&hls_root).await?;
-    let config = load_config().await?;
-
-    let mut tv_files = Vec::new();
-    if !config.shows.is_empty() {
-        for episodes in config.shows.values() {
-            for episode in episodes {
-                tv_files.push(episode.file_path.clone());
-            }
-        }
+    let config = config::load_config().await?;
+    let (mut cursors, mut migrated) = match config::load_cursors().await? {
+        Some(cursors) => (cursors, false),
+        None => (cursors::migrate_played_episodes(&config.played_episodes, &config.shows), true),
+    };
+    migrated |= cursors::fill_legacy_aired(&mut cursors, &config.shows);
+    migrated |= cursors::prune_aired(&mut cursors, &config.shows);
+
+    let tv_files = video::available_files(&config.shows);
+    if !tv_files.is_empty() {
         println!("Loaded {} video files from config", tv_files.len());
     }
 
     let state = Arc::new(AppState {
-        tv_files: RwLock::new(tv_files.clone()),
+        tv_files: RwLock::new(tv_files),
         hls_root: hls_root.clone(),
         jobs: RwLock::new(HashMap::new()),
-        videos_folder: RwLock::new(config.videos_folder.clone()),
+        scan_jobs: RwLock::new(HashMap::new()),
+        libraries: RwLock::new(config.libraries.clone()),
+        watch_library: RwLock::new(config.watch_library),
+        parse_rules: RwLock::new(config.parse_rules.clone()),
+        scan_settings: RwLock::new(config.scan_settings.clone()),
+        overrides: RwLock::new(config.overrides.clone()),
+        show_metadata: RwLock::new(config.show_metadata.clone()),
+        file_health: RwLock::new(config.file_health.clone()),
+        health_job: RwLock::new(None),
+        library_stats: RwLock::new(None),
+        library_watcher: RwLock::new(None),
         shows: RwLock::new(config.shows.clone()),
         playlist: RwLock::new(config.playlist.clone()),
-        played_episodes: Arc::new(RwLock::new(config.played_episodes.clone())),
+        active_playlist: RwLock::new(config.active_playlist.clone()),
+        playlists: RwLock::new(config.playlists.clone()),
+        cursors: RwLock::new(cursors),
+        cursors_changed: RwLock::new(false),
         subtitle_mode: RwLock::new(config.subtitle_mode.clone()),
+        playlist_end: RwLock::new(config.playlist_end),
+        channel_rotation: RwLock::new(config.channel_rotation),
         current_playing: RwLock::new(None),
+        now_playing: RwLock::new(None),
+        play_queue: RwLock::new(VecDeque::new()),
+        channel_commands: RwLock::new(None),
         is_playing: RwLock::new(false),
     });
 
+    // Write the cursors before the config drops the old played-episode lists
+    if migrated && let Err(e) = config::save_cursors(&state).await {
+        eprintln!("[tv] Failed to save cursors: {}", e);
+    }
+
     println!("Starting Rurushi HLS Server with Axum API + Next.js WebUI...");
     println!("HLS output directory: {}", hls_root.display());
 
@@ -76,6 +87,9 @@ async fn main() -> Result<()> {
         streaming::stop_streaming(state_clone).await;
     });
 
+    watcher::restart_library_watcher(state.clone()).await;
+    health::spawn_health_scheduler(state.clone());
+
     start_http_server(state, hls_root).await?;
 
     Ok(())
@@ -97,13 +111,8 @@ async fn start_http_server(state: Arc<AppState>, hls_root: std::path::PathBuf) -
     let webui_path_exe = exe_dir.join("webui").join("out");
     let webui_path_cwd = std::path::PathBuf::from("webui/out");
 
-    let webui_path = if webui_path_exe.exists() {
-        webui_path_exe
-    } else if webui_path_cwd.exists() {
-        webui_path_cwd
-    } else {
-        webui_path_cwd // Default to cwd path even if it doesn't exist
-    };
+    // Default to cwd path even if it doesn't exist
+    let webui_path = if webui_path_exe.exists() { webui_path_exe } else { webui_path_cwd };
 
     let has_static_webui = webui_path.exists();
 
@@ -115,17 +124,79 @@ async fn start_http_server(state: Arc<AppState>, hls_root: std::path::PathBuf) -
         // API endpoints
         .route("/api/config", get(api::get_config))
         .route("/api/folder", post(api::set_folder))
+        .route("/api/libraries", get(api::get_libraries).post(api::add_library))
+        .route("/api/libraries/{id}", put(api::update_library).delete(api::remove_library))
+        .route("/api/libraries/{id}/scan", post(api::scan_library))
+        .route("/api/library/search", get(api::search_library))
+        .route("/api/library/stats", get(api::get_library_stats))
+        .route("/api/library/health", get(api::get_library_health))
+        .route("/api/library/health/check", post(api::check_library_health))
+        .route("/api/library/health/cancel", post(api::cancel_health_check))
+        .route("/api/library/health/{file_id}", delete(api::clear_file_health))
+        .route("/api/overrides", get(api::get_overrides))
+        .route(
+            "/api/overrides/files/{file_id}",
+            put(api::set_file_override).delete(api::remove_file_override)
+        )
+        .route(
+            "/api/overrides/shows/{name}",
+            put(api::rename_show).delete(api::remove_show_rename)
+        )
+        .route("/api/artwork/shows/{name}/{kind}", get(handlers::show_artwork))
+        .route("/api/artwork/episodes/{file_id}/{kind}", get(handlers::episode_artwork))
         .route("/api/scan", post(api::scan_videos))
+        .route("/api/scan/preview", get(api::preview_parse))
+        .route("/api/scan/settings", get(api::get_scan_settings).put(api::set_scan_settings))
+        .route("/api/scan/{job}", get(api::get_scan_job))
+        .route("/api/scan/{job}/cancel", post(api::cancel_scan_job))
         .route("/api/files", get(api::get_files))
         .route("/api/shows", get(api::get_shows))
         .route("/api/play", post(api::play_video))
         .route("/api/stop", post(api::stop_playback))
         .route("/api/start-streaming", post(api::start_streaming))
         .route("/api/subtitle-mode", post(api::set_subtitle_mode))
-        .route("/api/playlist", get(api::get_playlist))
+        .route("/api/watch", post(api::set_watch_library))
+        .route("/api/playlists", get(api::get_playlists).post(api::create_playlist))
+        .route("/api/playlists/{name}", delete(api::delete_playlist))
+        .route("/api/playlists/{name}/duplicate", post(api::duplicate_playlist))
+        .route("/api/playlists/{name}/rename", post(api::rename_playlist))
+        .route(
+            "/api/playlists/{name}/items",
+            get(api::get_playlist).put(api::replace_playlist).delete(api::clear_playlist)
+        )
+        .route("/api/playlists/{name}/items/add", post(api::add_to_playlist))
+        .route("/api/playlists/{name}/items/move", post(api::move_playlist_item))
+        .route(
+            "/api/playlists/{name}/items/{index}",
+            delete(api::remove_from_playlist).patch(api::update_playlist_item)
+        )
+        .route("/api/playlists/{name}/items/{index}/move", post(api::move_playlist_item_to))
+        .route("/api/playlists/{name}/items/{index}/reshuffle", post(api::reshuffle_playlist_item))
+        .route("/api/channels/{channel}/playlist", post(api::activate_playlist))
+        .route("/api/playlist", get(api::get_playlist).put(api::replace_playlist))
         .route("/api/playlist/add", post(api::add_to_playlist))
-        .route("/api/playlist/{index}", delete(api::remove_from_playlist))
+        .route(
+            "/api/playlist/{index}",
+            delete(api::remove_from_playlist).patch(api::update_playlist_item)
+        )
+        .route("/api/playlist/{index}/move", post(api::move_playlist_item_to))
         .route("/api/playlist/move", post(api::move_playlist_item))
+        .route("/api/playlist/end-policy", post(api::set_playlist_end_policy))
+        .route("/api/playlist/rotation", post(api::set_channel_rotation))
+        .route("/api/playlist/{index}/reshuffle", post(api::reshuffle_playlist_item))
+        .route("/api/now-next", get(api::get_now_next))
+        .route("/api/queue", get(api::get_queue).post(api::add_to_queue).delete(api::clear_queue))
+        .route("/api/queue/{index}", delete(api::remove_from_queue))
+        .route("/api/channel/{id}/skip", post(api::skip_episode))
+        .route("/api/channel/{id}/previous", post(api::previous_episode))
+        .route("/api/channel/{id}/restart", post(api::restart_episode))
+        .route("/api/channel/{id}/seek", post(api::seek_episode))
+        .route("/api/channels/{channel}/cursors", get(api::get_cursors))
+        .route(
+            "/api/channels/{channel}/cursors/{show}",
+            put(api::jump_cursor).delete(api::reset_cursor)
+        )
+        .route("/api/channels/{channel}/cursors/{show}/rewind", post(api::rewind_cursor))
         .route("/api/playlist", delete(api::clear_playlist))
         .layer(cors)
         .with_state(state);
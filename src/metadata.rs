@@ -0,0 +1,205 @@
+use std::{ collections::{ HashMap, HashSet }, fs, path::{ Path, PathBuf } };
+
+use crate::models::{ Episode, MediaMetadata };
+use crate::video::VideoFile;
+
+const SHOW_NFO: &str = "tvshow.nfo";
+const MOVIE_NFO: &str = "movie.nfo";
+const IMAGE_EXTENSIONS: [&str; 4] = ["jpg", "jpeg", "png", "webp"];
+
+/// `<stem>.nfo` next to a media file (or a movie folder's `movie.nfo`) plus
+/// its thumbnail or poster (`<stem>-thumb.jpg`, `<stem>-poster.jpg`, `<stem>.jpg`)
+pub fn read_episode_metadata(path: &Path) -> Option<MediaMetadata> {
+    let stem = path.file_stem()?.to_string_lossy();
+    let dir = path.parent()?;
+
+    let mut metadata = read_nfo(&path.with_extension("nfo"))
+        .or_else(|| read_nfo(&dir.join(MOVIE_NFO)))
+        .unwrap_or_default();
+    metadata.poster = find_image(
+        dir,
+        &[&format!("{}-thumb", stem), &format!("{}-poster", stem), &stem]
+    );
+
+    (metadata != MediaMetadata::default()).then_some(metadata)
+}
+
+/// Show details per folder, cached for the duration of a scan
+#[derive(Default)]
+pub struct ShowMetadataCache {
+    dirs: HashMap<PathBuf, (bool, Option<MediaMetadata>)>,
+}
+
+impl ShowMetadataCache {
+    /// Details for the show `file` belongs to: the closest folder up to `root`
+    /// with a `tvshow.nfo`, otherwise the closest one with artwork. Season
+    /// folders often carry their own `poster.jpg`, hence the preference.
+    pub fn lookup(&mut self, root: &Path, file: &Path) -> Option<MediaMetadata> {
+        let mut artwork_only = None;
+
+        for dir in file
+            .ancestors()
+            .skip(1)
+            .take_while(|dir| dir.starts_with(root)) {
+            let (has_nfo, metadata) = self.dirs
+                .entry(dir.to_path_buf())
+                .or_insert_with(|| read_show_dir(dir));
+
+            match metadata {
+                Some(metadata) if *has_nfo => {
+                    return Some(metadata.clone());
+                }
+                Some(metadata) if artwork_only.is_none() => {
+                    artwork_only = Some(metadata.clone());
+                }
+                _ => {}
+            }
+        }
+
+        artwork_only
+    }
+}
+
+/// `tvshow.nfo`, `poster.jpg`/`folder.jpg` and `fanart.jpg` in a show folder
+fn read_show_dir(dir: &Path) -> (bool, Option<MediaMetadata>) {
+    let nfo = read_nfo(&dir.join(SHOW_NFO));
+    let has_nfo = nfo.is_some();
+
+    let mut metadata = nfo.unwrap_or_default();
+    metadata.poster = find_image(dir, &["poster", "folder"]);
+    metadata.fanart = find_image(dir, &["fanart", "backdrop"]);
+
+    (has_nfo, (metadata != MediaMetadata::default()).then_some(metadata))
+}
+
+fn find_image(dir: &Path, names: &[&str]) -> Option<PathBuf> {
+    names
+        .iter()
+        .flat_map(|name| IMAGE_EXTENSIONS.iter().map(move |ext| dir.join(format!("{}.{}", name, ext))))
+        .find(|path| path.is_file())
+}
+
+fn read_nfo(path: &Path) -> Option<MediaMetadata> {
+    if !path.is_file() {
+        return None;
+    }
+
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) => {
+            eprintln!("[metadata] Failed to read {}: {}", path.display(), e);
+            return None;
+        }
+    };
+
+    match parse_nfo(&content) {
+        Ok(metadata) => Some(metadata),
+        Err(e) => {
+            eprintln!("[metadata] Failed to parse {}: {}", path.display(), e);
+            None
+        }
+    }
+}
+
+/// Read the common fields of a Kodi `<tvshow>`, `<episodedetails>` or `<movie>` NFO
+pub fn parse_nfo(content: &str) -> Result<MediaMetadata, String> {
+    // Kodi allows a scraper URL after the XML document; only the XML part is parsed
+    let end = content.rfind('>').map(|i| i + 1).unwrap_or(content.len());
+    let document = roxmltree::Document::parse(&content[..end]).map_err(|e| e.to_string())?;
+    let root = document.root_element();
+
+    let text = |node: roxmltree::Node| {
+        node.text()
+            .map(str::trim)
+            .filter(|t| !t.is_empty())
+            .map(String::from)
+    };
+    let child = |name: &str| {
+        root.children()
+            .find(|node| node.has_tag_name(name))
+            .and_then(text)
+    };
+
+    // Newer NFOs nest ratings as <ratings><rating default="true"><value>
+    let rating = child("rating")
+        .and_then(|r| r.parse().ok())
+        .or_else(|| {
+            let ratings: Vec<_> = root
+                .descendants()
+                .filter(|node| node.has_tag_name("rating") && node.parent_element() != Some(root))
+                .collect();
+            ratings
+                .iter()
+                .find(|node| node.attribute("default") == Some("true"))
+                .or(ratings.first())
+                .and_then(|node| node.children().find(|n| n.has_tag_name("value")))
+                .and_then(text)
+                .and_then(|r| r.parse().ok())
+        });
+
+    Ok(MediaMetadata {
+        title: child("title"),
+        plot: child("plot").or_else(|| child("outline")),
+        aired: child("aired")
+            .or_else(|| child("premiered"))
+            .or_else(|| child("year")),
+        genres: root
+            .children()
+            .filter(|node| node.has_tag_name("genre"))
+            .filter_map(text)
+            .collect(),
+        // <set><name>Collection</name></set>, or just <set>Collection</set> in older files
+        collection: root
+            .children()
+            .find(|node| node.has_tag_name("set"))
+            .and_then(|set| {
+                set.children()
+                    .find(|node| node.has_tag_name("name"))
+                    .and_then(text)
+                    .or_else(|| text(set))
+            }),
+        rating,
+        poster: None,
+        fanart: None,
+    })
+}
+
+/// Record show-level metadata found by a scan under the show names its files
+/// ended up in. Shows touched by the scan without any metadata lose their entry.
+pub fn update_show_metadata(
+    target: &mut HashMap<String, MediaMetadata>,
+    shows: &HashMap<String, Vec<Episode>>,
+    scanned: &[VideoFile]
+) {
+    let show_by_path: HashMap<&Path, &str> = shows
+        .iter()
+        .flat_map(|(name, episodes)| {
+            episodes.iter().map(move |episode| (episode.file_path.as_path(), name.as_str()))
+        })
+        .collect();
+
+    let mut touched = HashSet::new();
+    let mut found: HashMap<&str, &MediaMetadata> = HashMap::new();
+    for file in scanned {
+        let Some(&show) = show_by_path.get(file.path.as_path()) else {
+            continue;
+        };
+        touched.insert(show);
+        if let Some(metadata) = &file.show_metadata {
+            found.entry(show).or_insert(metadata);
+        }
+    }
+
+    for show in touched {
+        match found.get(show) {
+            Some(&metadata) => {
+                target.insert(show.to_string(), metadata.clone());
+            }
+            None => {
+                target.remove(show);
+            }
+        }
+    }
+
+    target.retain(|name, _| shows.contains_key(name));
+}
@@ -1,17 +1,305 @@
-use std::{ collections::HashMap, path::PathBuf, sync::Arc };
+use std::{
+    collections::{ BTreeMap, BTreeSet, HashMap, VecDeque },
+    fmt,
+    ops::RangeInclusive,
+    path::PathBuf,
+    str::FromStr,
+    sync::Arc,
+};
 
+use notify::RecommendedWatcher;
 use serde::{ Deserialize, Serialize };
-use tokio::{ sync::RwLock, task::JoinHandle };
+use tokio::{ sync::{ mpsc::UnboundedSender, RwLock }, task::JoinHandle };
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+use crate::health::HealthJob;
+use crate::scan::ScanJob;
+use crate::stats::LibraryStats;
+use crate::video::{ AUDIO_EXTENSIONS, VIDEO_EXTENSIONS };
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
 pub enum SubtitleMode {
+    #[default]
     None,
     Smart,
 }
 
-impl Default for SubtitleMode {
+/// What the channel does with a playlist item once its episodes have played
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PlayMode {
+    /// Play each episode once, then advance. Later passes continue after the
+    /// show's cursor instead of starting over.
+    Once,
+    /// Play the episodes `repeat_count` times, then advance
+    Repeat,
+    /// Keep playing this item; the channel never advances past it
+    Loop,
+    /// Play the episodes once, then stop the channel
+    OnceThenStop,
+}
+
+/// Order in which a playlist item's episodes are played
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PlayOrder {
+    #[default]
+    Sequential,
+    /// The same seeded permutation on every pass, see `PlaylistItem::seed`
+    Shuffle,
+    /// A new permutation on every pass, so nothing repeats until all episodes played
+    Random,
+    /// This many random episodes per pass
+    Pick(usize),
+}
+
+/// How the channel moves between playlist items
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ChannelRotation {
+    /// Finish each item before moving on to the next
+    #[default]
+    Sequential,
+    /// One episode from each item in turn
+    RoundRobin,
+    /// This many episodes from each item in turn
+    Blocks(usize),
+    /// A random item for every episode, weighted by `PlaylistItem::weight`
+    Weighted,
+}
+
+/// What the channel does after the last playlist item
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PlaylistEndPolicy {
+    /// Start again from the first item, with `Once` items playing their
+    /// episodes again; stops if nothing could play at all
+    #[default]
+    Restart,
+    /// Reset every show's cursor, then start again from the first item
+    Reset,
+    /// Stop the channel
+    Stop,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LibraryKind {
+    #[default]
+    Series,
+    Movies,
+    Music,
+}
+
+/// How the show (or album) name is derived for files in a library root
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ShowNaming {
+    /// Name of the directory directly containing the file
+    #[default]
+    ParentFolder,
+    /// Name of the first directory below the library root
+    TopFolder,
+    /// Text before the episode number in the file name
+    FileName,
+}
+
+/// What a custom parse rule is matched against
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RuleTarget {
+    /// File name without extension
+    #[default]
+    FileName,
+    /// Path relative to the library root, `/`-separated and without extension
+    Path,
+}
+
+/// User-defined regex with named captures `show`, `season`, `episode` and `title`
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ParseRule {
+    pub pattern: String,
+    #[serde(default)]
+    pub target: RuleTarget,
+}
+
+/// Details read from Kodi/Jellyfin-style `.nfo` files and artwork next to the media
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct MediaMetadata {
+    pub title: Option<String>,
+    pub plot: Option<String>,
+    /// Air or premiere date as written in the NFO, usually `YYYY-MM-DD`
+    pub aired: Option<String>,
+    #[serde(default)]
+    pub genres: Vec<String>,
+    /// Movie set/collection name
+    #[serde(default)]
+    pub collection: Option<String>,
+    pub rating: Option<f32>,
+    pub poster: Option<PathBuf>,
+    pub fanart: Option<PathBuf>,
+}
+
+/// Stream details reported by ffprobe
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct MediaInfo {
+    /// Length in seconds
+    pub duration: Option<f64>,
+    pub video_codec: Option<String>,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub audio_codec: Option<String>,
+    #[serde(default)]
+    pub subtitle_codecs: Vec<String>,
+}
+
+/// Outcome of the last integrity check of a file
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct FileHealth {
+    /// Problems found; empty when the file checked out fine
+    pub errors: Vec<String>,
+    /// Whether sample segments were decoded rather than only probed
+    pub decoded: bool,
+    /// Seconds since the Unix epoch
+    pub checked_at: u64,
+    /// Size and modification time at the time of the check
+    pub file_size: u64,
+    pub modified: Option<u64>,
+}
+
+impl FileHealth {
+    pub fn is_broken(&self) -> bool {
+        !self.errors.is_empty()
+    }
+
+    /// A file that changed since it was checked has to be checked again
+    pub fn is_current(&self, episode: &Episode) -> bool {
+        self.file_size == episode.file_size && self.modified == episode.modified
+    }
+}
+
+/// Where a show stands on a channel: the last episode that started airing
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct ShowCursor {
+    /// `Episode::file_id` of the last aired episode
+    pub file_id: String,
+    /// Its index in the show's episode order, used if the file has since disappeared
+    pub position: usize,
+    /// Whether it aired to the end
+    pub finished: bool,
+    /// How far into the episode the channel got, in seconds, while it has not finished
+    #[serde(default)]
+    pub offset: f64,
+    /// Seconds since the Unix epoch
+    pub updated_at: u64,
+    /// `Episode::file_id` of every episode that aired to the end or was
+    /// skipped. Shuffled shows air in any order, so this is not simply the
+    /// episodes before the cursor. Files that leave the library are pruned,
+    /// so it never holds more than the show. `None` in cursor files written
+    /// before it existed, until `fill_legacy_aired` runs.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub aired: Option<BTreeSet<String>>,
+}
+
+/// Channel id -> show name -> cursor
+pub type Cursors = HashMap<String, HashMap<String, ShowCursor>>;
+
+/// Manual corrections for a single file, applied on top of every scan
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct EpisodeOverride {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub show_name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub season: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub episode_number: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(default)]
+    pub hidden: bool,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Overrides {
+    /// Parsed show name -> name to use instead. Renaming onto an existing
+    /// show merges the two.
+    #[serde(default)]
+    pub shows: HashMap<String, String>,
+    /// Per-file corrections keyed by `Episode::file_id`
+    #[serde(default)]
+    pub files: HashMap<String, EpisodeOverride>,
+}
+
+/// Marker for episodes outside the regular numbering (sorted as season 0)
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SpecialKind {
+    Ova,
+    Oad,
+    Ona,
+    Special,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct LibraryRoot {
+    pub id: String,
+    pub path: PathBuf,
+    #[serde(default)]
+    pub kind: LibraryKind,
+    /// Glob patterns matched against paths relative to the root
+    #[serde(default)]
+    pub ignore: Vec<String>,
+    #[serde(default)]
+    pub naming: ShowNaming,
+    /// Disabled roots are skipped by scans and the watcher
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+}
+
+/// Which files a scan picks up, shared by all library roots
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct ScanSettings {
+    pub video_extensions: Vec<String>,
+    pub audio_extensions: Vec<String>,
+    /// Include dot-files and dot-folders, e.g. macOS `._` resource forks
+    pub include_hidden: bool,
+    pub follow_symlinks: bool,
+    /// Files smaller than this many bytes are skipped
+    pub min_file_size: u64,
+    /// Folder names skipped anywhere below a root, case-insensitive
+    pub excluded_folders: Vec<String>,
+    /// Skip `sample.mkv`, `Movie-sample.mkv` and the like
+    pub skip_samples: bool,
+    /// Run ffprobe on new and changed files
+    pub probe_media: bool,
+    /// Maximum number of ffprobe processes running at once
+    pub probe_workers: usize,
+    /// Hours between scheduled integrity checks; 0 disables them
+    pub verify_interval_hours: u64,
+    /// Decode a few seconds at the start, middle and end of each file during
+    /// scheduled checks instead of only probing it
+    pub verify_decode: bool,
+}
+
+impl Default for ScanSettings {
     fn default() -> Self {
-        SubtitleMode::None
+        let strings = |list: &[&str]| list.iter().map(|s| s.to_string()).collect();
+        Self {
+            video_extensions: strings(&VIDEO_EXTENSIONS),
+            audio_extensions: strings(&AUDIO_EXTENSIONS),
+            include_hidden: false,
+            follow_symlinks: false,
+            min_file_size: 0,
+            excluded_folders: strings(
+                &["Extras", "Featurettes", "Behind The Scenes", "Deleted Scenes", "Trailers", "Samples"]
+            ),
+            skip_samples: true,
+            probe_media: true,
+            probe_workers: 4,
+            verify_interval_hours: 0,
+            verify_decode: false,
+        }
+    }
+}
+
+impl ScanSettings {
+    pub fn extensions(&self, kind: LibraryKind) -> &[String] {
+        match kind {
+            LibraryKind::Series | LibraryKind::Movies => &self.video_extensions,
+            LibraryKind::Music => &self.audio_extensions,
+        }
     }
 }
 
@@ -19,12 +307,37 @@ pub struct AppState {
     pub tv_files: RwLock<Vec<PathBuf>>,
     pub hls_root: PathBuf,
     pub jobs: RwLock<HashMap<String, JoinHandle<()>>>,
-    pub videos_folder: RwLock<Option<PathBuf>>,
+    pub scan_jobs: RwLock<HashMap<String, Arc<ScanJob>>>,
+    pub libraries: RwLock<Vec<LibraryRoot>>,
+    pub watch_library: RwLock<bool>,
+    pub parse_rules: RwLock<Vec<ParseRule>>,
+    pub scan_settings: RwLock<ScanSettings>,
+    pub overrides: RwLock<Overrides>,
+    pub show_metadata: RwLock<HashMap<String, MediaMetadata>>,
+    pub file_health: RwLock<HashMap<String, FileHealth>>,
+    pub health_job: RwLock<Option<Arc<HealthJob>>>,
+    /// Computed on request, cleared whenever `shows` changes
+    pub library_stats: RwLock<Option<Arc<LibraryStats>>>,
+    pub library_watcher: RwLock<Option<RecommendedWatcher>>,
     pub shows: RwLock<HashMap<String, Vec<Episode>>>,
+    /// Items of the active playlist
     pub playlist: RwLock<Vec<PlaylistItem>>,
-    pub played_episodes: Arc<RwLock<HashMap<String, Vec<usize>>>>,
+    pub active_playlist: RwLock<String>,
+    /// Named playlists other than the active one
+    pub playlists: RwLock<BTreeMap<String, Vec<PlaylistItem>>>,
+    pub cursors: RwLock<Cursors>,
+    /// Set when cursors are edited through the API so the channel reloads its queues
+    pub cursors_changed: RwLock<bool>,
     pub subtitle_mode: RwLock<SubtitleMode>,
+    pub playlist_end: RwLock<PlaylistEndPolicy>,
+    pub channel_rotation: RwLock<ChannelRotation>,
     pub current_playing: RwLock<Option<PathBuf>>,
+    pub now_playing: RwLock<Option<NowPlaying>>,
+    /// Files to air before the schedule continues
+    pub play_queue: RwLock<VecDeque<ScheduledEpisode>>,
+    /// Transport controls for the file the TV channel is encoding; `None`
+    /// between files, so commands can never reach a later one
+    pub channel_commands: RwLock<Option<UnboundedSender<ChannelCommand>>>,
     pub is_playing: RwLock<bool>,
 }
 
@@ -34,33 +347,541 @@ pub struct Episode {
     pub name: String,
     pub file_path: PathBuf,
     pub show_name: String,
+    #[serde(default)]
+    pub season: Option<usize>,
+    /// For movies, the part number of a movie split over several files
     pub episode_number: Option<usize>,
+    /// Last episode contained in a batch file ("01-02")
+    #[serde(default)]
+    pub episode_end: Option<usize>,
+    /// Fractional part of in-between episodes ("12.5")
+    #[serde(default)]
+    pub sub_episode: Option<u32>,
+    #[serde(default)]
+    pub special: Option<SpecialKind>,
+    #[serde(default)]
+    pub title: Option<String>,
+    /// Release year, set for movies
+    #[serde(default)]
+    pub year: Option<u16>,
+    /// Id of the library root the file was found in
+    #[serde(default)]
+    pub library_id: String,
+    /// Stable identity derived from the file name and size, survives moves
+    #[serde(default)]
+    pub file_id: String,
+    #[serde(default)]
+    pub file_size: u64,
+    /// Last modification time in seconds since the Unix epoch
+    #[serde(default)]
+    pub modified: Option<u64>,
+    /// False once the file has disappeared from disk; kept so metadata survives
+    #[serde(default = "default_true")]
+    pub available: bool,
+    /// Hidden through an override; kept in the library but never played
+    #[serde(default)]
+    pub hidden: bool,
+    #[serde(default)]
+    pub metadata: Option<MediaMetadata>,
+    /// Filled in by probing during scans; `None` until probed
+    #[serde(default)]
+    pub media_info: Option<MediaInfo>,
 }
 
-#[derive(Serialize, Deserialize, Clone)]
+fn default_true() -> bool {
+    true
+}
+
+const DEFAULT_PLAYLIST_NAME: &str = "Default";
+
+fn default_playlist_name() -> String {
+    DEFAULT_PLAYLIST_NAME.to_string()
+}
+
+/// An episode by its numbers, written "S1E5"; a bare "5" means season 1.
+/// Specials are season 0.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct EpisodeRef {
+    pub season: usize,
+    pub episode: usize,
+}
+
+impl EpisodeRef {
+    /// First and last episode a file holds (batch files hold several);
+    /// `None` if it has no episode number
+    pub fn span(episode: &Episode) -> Option<RangeInclusive<EpisodeRef>> {
+        let season = episode.season.unwrap_or(1);
+        let first = episode.episode_number?;
+        let last = episode.episode_end.unwrap_or(first).max(first);
+        Some(EpisodeRef { season, episode: first }..=EpisodeRef { season, episode: last })
+    }
+}
+
+impl fmt::Display for EpisodeRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "S{}E{}", self.season, self.episode)
+    }
+}
+
+impl FromStr for EpisodeRef {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        let text = s.trim().to_ascii_uppercase();
+        let numbers = match text.strip_prefix('S').and_then(|rest| rest.split_once('E')) {
+            Some((season, episode)) => season.parse().ok().zip(episode.parse().ok()),
+            None => text.parse().ok().map(|episode| (1, episode)),
+        };
+        numbers
+            .map(|(season, episode)| EpisodeRef { season, episode })
+            .ok_or_else(|| format!("Invalid episode '{}', expected something like S1E5", s.trim()))
+    }
+}
+
+impl TryFrom<String> for EpisodeRef {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, String> {
+        s.parse()
+    }
+}
+
+impl From<EpisodeRef> for String {
+    fn from(episode: EpisodeRef) -> Self {
+        episode.to_string()
+    }
+}
+
+/// Inclusive range of episodes, written "S1E5-S2E3". An end without a
+/// season ("S2E1-4" or "S2E1-E4") stays in the season of the start.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct EpisodeRange {
+    pub from: EpisodeRef,
+    pub to: EpisodeRef,
+}
+
+impl fmt::Display for EpisodeRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.from == self.to {
+            write!(f, "{}", self.from)
+        } else {
+            write!(f, "{}-{}", self.from, self.to)
+        }
+    }
+}
+
+impl FromStr for EpisodeRange {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        // Also accept the en dash people copy from episode guides
+        let (from, to) = s.split_once(['-', '\u{2013}']).unwrap_or((s, s));
+        let from: EpisodeRef = from.parse()?;
+        let bare = to.trim();
+        let bare = bare.strip_prefix(['E', 'e']).unwrap_or(bare);
+        let to = match bare.parse() {
+            Ok(episode) => EpisodeRef { season: from.season, episode },
+            Err(_) => to.parse()?,
+        };
+        if to < from {
+            return Err(format!("Episode range '{}' ends before it starts", s.trim()));
+        }
+        Ok(EpisodeRange { from, to })
+    }
+}
+
+impl TryFrom<String> for EpisodeRange {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, String> {
+        s.parse()
+    }
+}
+
+impl From<EpisodeRange> for String {
+    fn from(range: EpisodeRange) -> Self {
+        range.to_string()
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
 pub struct PlaylistItem {
+    /// Empty when the item selects by collection or genre instead
+    #[serde(default)]
     pub show_name: String,
+    /// Every programme in this movie collection, ordered by year
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub collection: Option<String>,
+    /// Every programme with this genre, ordered by year
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub genre: Option<String>,
+    /// Zero-based, end-exclusive slice of older configs, replaced by `range`
+    /// when the config loads
+    #[serde(default, skip_serializing)]
     pub episode_range: Option<(usize, usize)>,
+    /// Episodes to play by number; together with `episodes`, or everything
+    /// if neither is set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub range: Option<EpisodeRange>,
+    /// Single episodes to play besides `range`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub episodes: Vec<EpisodeRef>,
+    /// Episodes left out even when `range` or `episodes` include them
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub exclude: Vec<EpisodeRef>,
     pub repeat_count: usize,
+    /// `None` in older configs, see `play_mode`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mode: Option<PlayMode>,
+    #[serde(default)]
+    pub order: PlayOrder,
+    /// Seed of the `Shuffle` permutation, kept so the order survives restarts
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
+    /// Share of airtime under `ChannelRotation::Weighted`; 1 if unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub weight: Option<u32>,
 }
 
-#[derive(Serialize, Deserialize, Clone)]
+impl PlaylistItem {
+    /// Items saved before play modes existed only had `repeat_count`, where 0
+    /// meant "play unplayed episodes once"
+    pub fn play_mode(&self) -> PlayMode {
+        match self.mode {
+            Some(mode) => mode,
+            None if self.repeat_count == 0 => PlayMode::Once,
+            None => PlayMode::Repeat,
+        }
+    }
+
+    /// Whether the episode selection (`range`, `episodes`, `exclude`)
+    /// keeps this episode. Unnumbered episodes only play when neither
+    /// `range` nor `episodes` is set.
+    pub fn selects(&self, episode: &Episode) -> bool {
+        let everything = self.range.is_none() && self.episodes.is_empty();
+        let Some(span) = EpisodeRef::span(episode) else {
+            return everything;
+        };
+        if self.exclude.iter().any(|excluded| span.contains(excluded)) {
+            return false;
+        }
+        everything ||
+            self.range.is_some_and(|range| *span.start() <= range.to && range.from <= *span.end()) ||
+            self.episodes.iter().any(|listed| span.contains(listed))
+    }
+
+    /// Human-readable description for logs
+    pub fn label(&self) -> String {
+        match (&self.collection, &self.genre) {
+            (Some(collection), Some(genre)) => format!("collection '{}' ({})", collection, genre),
+            (Some(collection), None) => format!("collection '{}'", collection),
+            (None, Some(genre)) => format!("genre '{}'", genre),
+            (None, None) => format!("show '{}'", self.show_name),
+        }
+    }
+}
+
+/// An episode as listed in the now/next data
+#[derive(Serialize, Clone)]
+pub struct ScheduledEpisode {
+    pub show_name: String,
+    pub name: String,
+    pub file_path: PathBuf,
+}
+
+/// Transport controls for the episode the TV channel is playing
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ChannelCommand {
+    Skip,
+    /// Go back to the previous episode, or restart the current one if there is none
+    Previous,
+    Restart,
+    /// Continue the current episode from this many seconds in
+    Seek(f64),
+    /// Make way for the file at the front of the play-next queue
+    PlayQueued,
+    /// Leave the current episode for the newly activated playlist
+    SwitchPlaylist,
+}
+
+/// What the TV channel is playing from the playlist and what comes next
+#[derive(Serialize, Clone)]
+pub struct NowPlaying {
+    /// Index of the playlist item being played; `None` for a queued file
+    pub item: Option<usize>,
+    pub order: Option<PlayOrder>,
+    /// Seconds since the Unix epoch; earlier than the actual start when the
+    /// episode was resumed part way through
+    pub started_at: u64,
+    pub now: ScheduledEpisode,
+    /// The play-next queue, then the rest of the schedule in play order
+    pub next: Vec<ScheduledEpisode>,
+}
+
+impl ScheduledEpisode {
+    pub fn of(episode: &Episode) -> Self {
+        Self {
+            show_name: episode.show_name.clone(),
+            name: episode.name.clone(),
+            file_path: episode.file_path.clone(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Default)]
 pub struct AppConfig {
+    /// Single-folder setting from older configs, migrated into `libraries` on load
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub videos_folder: Option<PathBuf>,
+    #[serde(default)]
+    pub libraries: Vec<LibraryRoot>,
     pub shows: HashMap<String, Vec<Episode>>,
+    /// Items of the active playlist
     pub playlist: Vec<PlaylistItem>,
+    #[serde(default = "default_playlist_name")]
+    pub active_playlist: String,
+    /// The other named playlists
+    #[serde(default)]
+    pub playlists: BTreeMap<String, Vec<PlaylistItem>>,
+    /// Replaced by `Cursors`; only read to migrate older configs
+    #[serde(default, skip_serializing)]
     pub played_episodes: HashMap<String, Vec<usize>>,
     pub subtitle_mode: SubtitleMode,
+    #[serde(default)]
+    pub playlist_end: PlaylistEndPolicy,
+    #[serde(default)]
+    pub channel_rotation: ChannelRotation,
+    /// Watch the videos folder and update the library as files change
+    #[serde(default)]
+    pub watch_library: bool,
+    /// Tried in order before the built-in file name heuristics
+    #[serde(default)]
+    pub parse_rules: Vec<ParseRule>,
+    #[serde(default)]
+    pub scan_settings: ScanSettings,
+    #[serde(default)]
+    pub overrides: Overrides,
+    /// Show-level NFO details and artwork, keyed by show name
+    #[serde(default)]
+    pub show_metadata: HashMap<String, MediaMetadata>,
+    /// Integrity check results keyed by `Episode::file_id`
+    #[serde(default)]
+    pub file_health: HashMap<String, FileHealth>,
 }
 
-impl Default for AppConfig {
-    fn default() -> Self {
-        Self {
-            videos_folder: None,
-            shows: HashMap::new(),
-            playlist: Vec::new(),
-            played_episodes: HashMap::new(),
-            subtitle_mode: SubtitleMode::default(),
+/// Library and playlist building blocks shared by the unit tests
+#[cfg(test)]
+pub mod fixtures {
+    use super::*;
+
+    /// An available episode of `show`, named "{show} {label}"
+    fn named(show: &str, label: &str) -> Episode {
+        let name = format!("{} {}", show, label);
+        Episode {
+            id: 0,
+            file_path: PathBuf::from(format!("/library/{}/{}.mkv", show, name)),
+            file_id: name.clone(),
+            name,
+            show_name: show.to_string(),
+            season: None,
+            episode_number: None,
+            episode_end: None,
+            sub_episode: None,
+            special: None,
+            title: None,
+            year: None,
+            library_id: "library".to_string(),
+            file_size: 0,
+            modified: None,
+            available: true,
+            hidden: false,
+            metadata: None,
+            media_info: None,
+        }
+    }
+
+    /// A numbered episode named like "A S1E2"
+    pub fn episode(show: &str, season: usize, number: usize) -> Episode {
+        let mut episode = named(show, &EpisodeRef { season, episode: number }.to_string());
+        episode.season = Some(season);
+        episode.episode_number = Some(number);
+        episode
+    }
+
+    /// A file holding episodes `first..=last`, named like "A S1E5-6"
+    pub fn batch(show: &str, season: usize, first: usize, last: usize) -> Episode {
+        let mut episode = named(show, &format!("S{}E{}-{}", season, first, last));
+        episode.season = Some(season);
+        episode.episode_number = Some(first);
+        episode.episode_end = Some(last);
+        episode
+    }
+
+    /// An episode without a number, named like "A Extra"
+    pub fn extra(show: &str, label: &str) -> Episode {
+        named(show, label)
+    }
+
+    /// Episodes `1..=count` of each season, with the counts of seasons 1, 2, ...
+    pub fn seasons(show: &str, counts: &[usize]) -> Vec<Episode> {
+        (1..)
+            .zip(counts)
+            .flat_map(|(season, &count)| (1..=count).map(move |number| episode(show, season, number)))
+            .collect()
+    }
+
+    /// Episodes grouped into shows, in the order given
+    pub fn shows(episodes: impl IntoIterator<Item = Episode>) -> HashMap<String, Vec<Episode>> {
+        let mut shows: HashMap<String, Vec<Episode>> = HashMap::new();
+        for episode in episodes {
+            shows.entry(episode.show_name.clone()).or_default().push(episode);
+        }
+        shows
+    }
+
+    /// Shows with episodes 1..=count of season 1 each
+    pub fn library(counts: &[(&str, usize)]) -> HashMap<String, Vec<Episode>> {
+        shows(counts.iter().flat_map(|&(show, count)| seasons(show, &[count])))
+    }
+
+    /// Every episode of `show`, played once in order
+    pub fn item(show: &str) -> PlaylistItem {
+        PlaylistItem {
+            show_name: show.to_string(),
+            collection: None,
+            genre: None,
+            episode_range: None,
+            range: None,
+            episodes: Vec::new(),
+            exclude: Vec::new(),
+            repeat_count: 0,
+            mode: Some(PlayMode::Once),
+            order: PlayOrder::Sequential,
+            seed: None,
+            weight: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fixtures::{ batch, extra, item, seasons };
+
+    fn at(season: usize, episode: usize) -> EpisodeRef {
+        EpisodeRef { season, episode }
+    }
+
+    #[test]
+    fn parses_episode_refs() {
+        let cases = [
+            ("S1E5", Some(at(1, 5))),
+            ("s2e03", Some(at(2, 3))),
+            (" S0E1 ", Some(at(0, 1))),
+            ("5", Some(at(1, 5))),
+            ("S10E120", Some(at(10, 120))),
+            ("", None),
+            ("S1", None),
+            ("E5", None),
+            ("S1E", None),
+            ("SxE1", None),
+            ("S1E5x", None),
+            ("-1", None),
+        ];
+        for (text, expected) in cases {
+            assert_eq!(text.parse::<EpisodeRef>().ok(), expected, "{:?}", text);
+        }
+
+        let error = "S1".parse::<EpisodeRef>().unwrap_err();
+        assert_eq!(error, "Invalid episode 'S1', expected something like S1E5");
+    }
+
+    #[test]
+    fn parses_episode_ranges() {
+        let cases = [
+            ("S1E5-S2E3", Some((at(1, 5), at(2, 3)))),
+            ("S1E5\u{2013}S1E8", Some((at(1, 5), at(1, 8)))),
+            ("S1E5 - S1E8", Some((at(1, 5), at(1, 8)))),
+            ("3-7", Some((at(1, 3), at(1, 7)))),
+            ("S2E1-4", Some((at(2, 1), at(2, 4)))),
+            ("S2E1-e4", Some((at(2, 1), at(2, 4)))),
+            ("S2E1\u{2013}E4", Some((at(2, 1), at(2, 4)))),
+            ("S1E4", Some((at(1, 4), at(1, 4)))),
+            ("4", Some((at(1, 4), at(1, 4)))),
+            ("S1E8-S1E2", None),
+            ("S2E3-1", None),
+            ("S2E1-S1E9", None),
+            ("S1E5-", None),
+            ("-S1E5", None),
+            ("S1E1-S1E2-S1E3", None),
+            ("S1E1..S1E3", None),
+        ];
+        for (text, expected) in cases {
+            let range = text.parse::<EpisodeRange>().ok();
+            assert_eq!(range.map(|range| (range.from, range.to)), expected, "{:?}", text);
+        }
+    }
+
+    #[test]
+    fn episode_selections_round_trip_through_text() {
+        for text in ["S1E5", "S0E1", "S1E5-S2E3", "S2E1-S2E4"] {
+            let range: EpisodeRange = text.parse().unwrap();
+            assert_eq!(range.to_string(), text);
+            let yaml = serde_yaml::to_string(&range).unwrap();
+            assert_eq!(serde_yaml::from_str::<EpisodeRange>(&yaml).unwrap(), range);
+        }
+        assert!(serde_yaml::from_str::<EpisodeRef>("S1").is_err());
+
+        let error = "S2E3-1".parse::<EpisodeRange>().unwrap_err();
+        assert_eq!(error, "Episode range 'S2E3-1' ends before it starts");
+        assert!(serde_yaml::from_str::<EpisodeRange>("S1E8-S1E2").is_err());
+    }
+
+    /// Space-separated episodes, for the tables below
+    fn refs(text: &str) -> Vec<EpisodeRef> {
+        text.split_whitespace().map(|text| text.parse().unwrap()).collect()
+    }
+
+    #[test]
+    fn selects_episodes_by_number() {
+        let mut library = seasons("A", &[4, 2]);
+        library.insert(4, batch("A", 1, 5, 6));
+        library.push(extra("A", "Extra"));
+
+        // Range, listed episodes, excluded episodes, and what plays
+        let cases = [
+            ("", "", "", "S1E1 S1E2 S1E3 S1E4 S1E5-6 S2E1 S2E2 Extra"),
+            ("S1E2-S1E3", "", "", "S1E2 S1E3"),
+            ("S1E4\u{2013}S2E1", "", "", "S1E4 S1E5-6 S2E1"),
+            // Batch files play if any of their episodes is wanted
+            ("S1E6", "", "", "S1E5-6"),
+            ("S1E6-S2E1", "", "", "S1E5-6 S2E1"),
+            ("", "S1E1 S2E2", "", "S1E1 S2E2"),
+            ("", "1 3", "", "S1E1 S1E3"),
+            ("S1E1-S1E3", "S2E2", "", "S1E1 S1E2 S1E3 S2E2"),
+            ("S1E1-S1E4", "", "S1E2", "S1E1 S1E3 S1E4"),
+            // Exclusions win over ranges and lists
+            ("", "S1E1 S1E3", "S1E3", "S1E1"),
+            ("S1E1-S1E2", "S1E2", "S1E2", "S1E1"),
+            ("S1E4-S2E1", "", "S1E6", "S1E4 S2E1"),
+            // Excluding alone still plays everything else, extras included
+            ("", "", "S1E1 S2E2", "S1E2 S1E3 S1E4 S1E5-6 S2E1 Extra"),
+            ("S3E1-S3E9", "", "", ""),
+        ];
+        for (range, episodes, exclude, expected) in cases {
+            let mut item = item("A");
+            item.range = (!range.is_empty()).then(|| range.parse().unwrap());
+            item.episodes = refs(episodes);
+            item.exclude = refs(exclude);
+
+            let selected: Vec<&str> = library
+                .iter()
+                .filter(|episode| item.selects(episode))
+                .map(|episode| &episode.name["A ".len()..])
+                .collect();
+            assert_eq!(selected.join(" "), expected, "{:?} + {:?} - {:?}", range, episodes, exclude);
         }
     }
 }
@@ -0,0 +1,63 @@
+use std::time::{ SystemTime, UNIX_EPOCH };
+
+use crate::models::{ PlayOrder, PlaylistItem };
+
+/// SplitMix64: tiny and fast, and more than random enough to shuffle a playlist
+#[derive(Clone)]
+pub struct SplitMix64(u64);
+
+impl SplitMix64 {
+    pub fn new(seed: u64) -> Self {
+        Self(seed)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Value in `0..bound`; the modulo bias is irrelevant at playlist sizes
+    pub fn below(&mut self, bound: usize) -> usize {
+        (self.next_u64() % (bound as u64)) as usize
+    }
+
+    /// Fisher-Yates shuffle
+    fn shuffle<T>(&mut self, items: &mut [T]) {
+        for i in (1..items.len()).rev() {
+            items.swap(i, self.below(i + 1));
+        }
+    }
+}
+
+/// A fresh seed from the clock, for new shuffles and random passes. Kept to
+/// 53 bits so it survives a round trip through JavaScript numbers.
+pub fn random_seed() -> u64 {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos() as u64)
+        .unwrap_or(0);
+    SplitMix64::new(nanos).next_u64() >> 11
+}
+
+/// Arrange one pass over a playlist item's episodes in the item's play order,
+/// leaving out those `keep` rejects. The whole selection is shuffled first,
+/// so that a seeded shuffle stays the same as episodes air or go missing.
+pub fn order_pass<T>(item: &PlaylistItem, mut episodes: Vec<T>, keep: impl FnMut(&T) -> bool) -> Vec<T> {
+    match item.order {
+        PlayOrder::Sequential => {}
+        PlayOrder::Shuffle => {
+            SplitMix64::new(item.seed.unwrap_or(0)).shuffle(&mut episodes);
+        }
+        PlayOrder::Random | PlayOrder::Pick(_) => {
+            SplitMix64::new(random_seed()).shuffle(&mut episodes);
+        }
+    }
+    episodes.retain(keep);
+    if let PlayOrder::Pick(count) = item.order {
+        episodes.truncate(count);
+    }
+    episodes
+}
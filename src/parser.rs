@@ -0,0 +1,569 @@
+use std::sync::LazyLock;
+
+use regex::{ Captures, Regex };
+
+use crate::models::{ ParseRule, RuleTarget, SpecialKind };
+
+/// Everything that can be read from a release file name
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ReleaseInfo {
+    pub group: Option<String>,
+    pub show: Option<String>,
+    pub season: Option<usize>,
+    pub episode: Option<usize>,
+    /// Last episode of a batch file such as "01-02"
+    pub episode_end: Option<usize>,
+    /// Fractional part of recap/special numbers such as "12.5"
+    pub sub_episode: Option<u32>,
+    pub version: Option<u32>,
+    pub special: Option<SpecialKind>,
+    pub title: Option<String>,
+    pub resolution: Option<String>,
+    pub crc: Option<String>,
+}
+
+static LEADING_GROUP: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^\s*\[([^\]]+)\]").unwrap());
+static BRACKETED: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"\[([^\]]*)\]|\(([^)]*)\)|\{([^}]*)\}").unwrap()
+});
+static CRC: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^[0-9A-Fa-f]{8}$").unwrap());
+static RESOLUTION: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)\b(\d{3,4}p|\d{3,4}x\d{3,4}|4k)\b").unwrap()
+});
+/// First token of the technical tail in scene-style names
+static QUALITY_TAG: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"(?i)\b(?:\d{3,4}p|\d{3,4}x\d{3,4}|4k|[xh][ .]?26[45]|hevc|avc|web-?dl|web-?rip|blu-?ray|bd-?rip|hdtv|dvd-?rip|10-?bit|aac|flac|ac3|dts|dual audio)\b"
+    ).unwrap()
+});
+static BRACKET_EPISODE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^(\d{1,4})(?:v(\d{1,2}))?$").unwrap()
+});
+static SEASON_EPISODE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"(?i)\bs(\d{1,3})[ ._-]?e(\d{1,4})(?:v(\d{1,2}))?(?:[ ._-]?-?[ ._-]?e(\d{1,4}))?\b"
+    ).unwrap()
+});
+static CROSS_EPISODE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)\b(\d{1,2})x(\d{2,3})\b").unwrap()
+});
+static JAPANESE_EPISODE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"第\s*(\d{1,4})\s*[話话集]").unwrap()
+});
+/// A whole " - " separated segment holding the episode, e.g. "01", "01v2", "12.5", "01-02", "Ep 3"
+static EPISODE_SEGMENT: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"(?i)^(?:(?:episode|ep|e)\.?\s*)?(\d{1,4})(?:\.(\d{1,2}))?(?:v(\d{1,2}))?(?:\s*[-~]\s*(\d{1,4})(?:v\d{1,2})?)?(?:\s+(?:end|final))?$"
+    ).unwrap()
+});
+static SPECIAL_SEGMENT: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)^(ova|oad|ona|sp|specials?)\s*(\d{1,3})?(?:v(\d{1,2}))?$").unwrap()
+});
+static SPECIAL_MARKER: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)\b(ova|oad|ona|sp|specials?)\s*(\d{1,3})?(?:v(\d{1,2}))?\b").unwrap()
+});
+static EPISODE_KEYWORD: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)\b(?:episode|ep)\.?\s*(\d{1,4})(?:v(\d{1,2}))?\b").unwrap()
+});
+static TRAILING_EPISODE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"\s(\d{1,4})(?:\.(\d{1,2}))?(?:v(\d{1,2}))?(?:\s*[-~]\s*(\d{1,4}))?$"
+    ).unwrap()
+});
+static WHITESPACE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\s+").unwrap());
+
+/// Parse a file stem following the usual fansub and scene naming conventions:
+/// `[Group] Show - 01v2 [1080p][ABCD1234]`, `Show.S01E02.Title.1080p.WEB-DL`,
+/// `Show - 12.5`, `Show - OVA 2`, `Show - 01-02` and `作品 第01話`.
+pub fn parse_release_name(stem: &str) -> ReleaseInfo {
+    let mut info = ReleaseInfo::default();
+    let mut text = stem.to_string();
+
+    if let Some(captures) = LEADING_GROUP.captures(&text) {
+        info.group = clean(&captures[1]);
+        text = text[captures.get(0).unwrap().end()..].to_string();
+    }
+
+    // Bracketed tags carry CRC/resolution/etc. and never belong to the show name
+    let mut bracket_episode = None;
+    for captures in BRACKETED.captures_iter(&text) {
+        let inner = captures
+            .iter()
+            .skip(1)
+            .flatten()
+            .next()
+            .map(|m| m.as_str().trim())
+            .unwrap_or("");
+
+        if CRC.is_match(inner) && info.crc.is_none() {
+            info.crc = Some(inner.to_uppercase());
+        } else if let Some(resolution) = RESOLUTION.find(inner) {
+            info.resolution.get_or_insert_with(|| resolution.as_str().to_lowercase());
+        } else if let Some(episode) = BRACKET_EPISODE.captures(inner) {
+            bracket_episode.get_or_insert((number(&episode, 1), number(&episode, 2)));
+        }
+    }
+    text = BRACKETED.replace_all(&text, " ").into_owned();
+
+    text = text.replace('_', " ");
+    if !text.trim().contains(' ') {
+        text = undot(&text);
+    }
+
+    if let Some(tag) = QUALITY_TAG.find(&text) {
+        if let Some(resolution) = RESOLUTION.find(&text[tag.start()..]) {
+            info.resolution.get_or_insert_with(|| resolution.as_str().to_lowercase());
+        }
+        text.truncate(tag.start());
+    }
+    let text = WHITESPACE.replace_all(&text, " ").trim().to_string();
+
+    if parse_season_episode(&text, &mut info) ||
+        parse_japanese(&text, &mut info) ||
+        parse_segments(&text, &mut info) ||
+        parse_special_marker(&text, &mut info) ||
+        parse_episode_keyword(&text, &mut info) ||
+        parse_trailing_number(&text, &mut info)
+    {
+        return info;
+    }
+
+    info.show = clean(&text);
+    if let Some((episode, version)) = bracket_episode {
+        info.episode = episode;
+        info.version = version;
+    }
+    info
+}
+
+/// "S01E02", "S01E01-E02", "1x02"
+fn parse_season_episode(text: &str, info: &mut ReleaseInfo) -> bool {
+    let Some(captures) = SEASON_EPISODE.captures(text).or_else(|| CROSS_EPISODE.captures(text)) else {
+        return false;
+    };
+
+    let whole = captures.get(0).unwrap();
+    info.season = number(&captures, 1);
+    info.episode = number(&captures, 2);
+    info.version = number(&captures, 3);
+    info.episode_end = number(&captures, 4);
+    info.show = clean(&text[..whole.start()]);
+    info.title = clean(&text[whole.end()..]);
+    true
+}
+
+/// "第01話"
+fn parse_japanese(text: &str, info: &mut ReleaseInfo) -> bool {
+    let Some(captures) = JAPANESE_EPISODE.captures(text) else {
+        return false;
+    };
+
+    let whole = captures.get(0).unwrap();
+    info.episode = number(&captures, 1);
+    info.show = clean(&text[..whole.start()]);
+    info.title = clean(&text[whole.end()..]);
+    true
+}
+
+/// "Show - 01v2 - Title", "Show 2 - 03", "Show - 12.5", "Show - OVA 2"
+fn parse_segments(text: &str, info: &mut ReleaseInfo) -> bool {
+    let segments: Vec<&str> = text.split(" - ").collect();
+
+    for index in 1..segments.len() {
+        let segment = segments[index].trim();
+
+        if let Some(captures) = EPISODE_SEGMENT.captures(segment) {
+            info.episode = number(&captures, 1);
+            info.sub_episode = number(&captures, 2);
+            info.version = number(&captures, 3);
+            info.episode_end = number(&captures, 4);
+        } else if let Some(captures) = SPECIAL_SEGMENT.captures(segment) {
+            info.special = special_kind(&captures[1]);
+            info.episode = number(&captures, 2);
+            info.version = number(&captures, 3);
+        } else {
+            continue;
+        }
+
+        info.show = clean(&segments[..index].join(" - "));
+        info.title = clean(&segments[index + 1..].join(" - "));
+        return true;
+    }
+
+    false
+}
+
+/// "Show OVA", "Show SP2"
+fn parse_special_marker(text: &str, info: &mut ReleaseInfo) -> bool {
+    let Some(captures) = SPECIAL_MARKER.captures(text) else {
+        return false;
+    };
+
+    let whole = captures.get(0).unwrap();
+    if whole.start() == 0 {
+        return false;
+    }
+
+    info.special = special_kind(&captures[1]);
+    info.episode = number(&captures, 2);
+    info.version = number(&captures, 3);
+    info.show = clean(&text[..whole.start()]);
+    info.title = clean(&text[whole.end()..]);
+    true
+}
+
+/// "Show Episode 5", "Show Ep.10"
+fn parse_episode_keyword(text: &str, info: &mut ReleaseInfo) -> bool {
+    let Some(captures) = EPISODE_KEYWORD.captures(text) else {
+        return false;
+    };
+
+    let whole = captures.get(0).unwrap();
+    info.episode = number(&captures, 1);
+    info.version = number(&captures, 2);
+    info.show = clean(&text[..whole.start()]);
+    info.title = clean(&text[whole.end()..]);
+    true
+}
+
+/// "Show Name 01", "Show 01v2", "Show 01-02"
+fn parse_trailing_number(text: &str, info: &mut ReleaseInfo) -> bool {
+    let Some(captures) = TRAILING_EPISODE.captures(text) else {
+        return false;
+    };
+
+    info.episode = number(&captures, 1);
+    info.sub_episode = number(&captures, 2);
+    info.version = number(&captures, 3);
+    info.episode_end = number(&captures, 4);
+    info.show = clean(&text[..captures.get(0).unwrap().start()]);
+    true
+}
+
+fn special_kind(marker: &str) -> Option<SpecialKind> {
+    match marker.to_lowercase().as_str() {
+        "ova" => Some(SpecialKind::Ova),
+        "oad" => Some(SpecialKind::Oad),
+        "ona" => Some(SpecialKind::Ona),
+        "sp" | "special" | "specials" => Some(SpecialKind::Special),
+        _ => None,
+    }
+}
+
+fn number<T: std::str::FromStr>(captures: &Captures, group: usize) -> Option<T> {
+    captures.get(group)?.as_str().parse().ok()
+}
+
+/// Turn "Show.Name.S01E02" into "Show Name S01E02" while keeping "12.5" intact
+fn undot(text: &str) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    chars
+        .iter()
+        .enumerate()
+        .map(|(i, &c)| {
+            // A decimal has one or two digits after the dot, unlike "2019.1080p"
+            let fraction = chars[i + 1..]
+                .iter()
+                .take_while(|n| n.is_ascii_digit())
+                .count();
+            let decimal =
+                i > 0 &&
+                chars[i - 1].is_ascii_digit() &&
+                (1..=2).contains(&fraction) &&
+                !chars.get(i + 1 + fraction).is_some_and(|n| n.is_alphanumeric());
+            if c == '.' && !decimal { ' ' } else { c }
+        })
+        .collect()
+}
+
+/// Trim separators and decoration left around a name or title
+fn clean(text: &str) -> Option<String> {
+    let trimmed = text.trim_matches(|c: char| {
+        c.is_whitespace() || "-_.~「」『』".contains(c)
+    });
+    (!trimmed.is_empty()).then(|| trimmed.to_string())
+}
+
+/// Title, year and part number read from a movie file or folder name
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct MovieInfo {
+    pub title: Option<String>,
+    pub year: Option<u16>,
+    /// Part of a movie split over several files ("cd1", "part2")
+    pub part: Option<usize>,
+}
+
+static BRACKET_YEAR: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"[(\[]((?:19|20)\d{2})[)\]]").unwrap()
+});
+static BARE_YEAR: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?:^|\s)((?:19|20)\d{2})(?:\s|$)").unwrap()
+});
+static MOVIE_PART: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)[\s-]*[(\[]?\b(?:cd|disc|disk|dvd|part|pt)[ -]?(\d{1,2})\b[)\]]?").unwrap()
+});
+
+/// Parse `Title (2019)`, `Title.2019.1080p.BluRay` or `Title (2019) cd1`.
+/// A year at the very start or end without brackets is taken as part of the
+/// title ("1917", "Blade Runner 2049").
+pub fn parse_movie_name(stem: &str) -> MovieInfo {
+    let mut text = LEADING_GROUP.replace(stem, "").replace('_', " ");
+    if !text.trim().contains(' ') {
+        text = undot(&text);
+    }
+    let text = WHITESPACE.replace_all(text.trim(), " ").into_owned();
+
+    let year = BRACKET_YEAR.captures_iter(&text)
+        .last()
+        .or_else(|| {
+            BARE_YEAR.captures_iter(&text)
+                .filter(|c| {
+                    let year = c.get(1).unwrap();
+                    year.start() > 0 && year.end() < text.len()
+                })
+                .last()
+        })
+        .map(|c| (c.get(0).unwrap().start(), c.get(0).unwrap().end(), number(&c, 1)));
+
+    // "Part 1" before the year belongs to the title ("Deathly Hallows Part 1 (2010)")
+    let part_from = year.map(|(_, end, _)| end).unwrap_or(0);
+    let part = MOVIE_PART.captures_iter(&text)
+        .filter(|c| c.get(0).unwrap().start() >= part_from)
+        .last();
+
+    let mut title = match year {
+        Some((start, _, _)) => text[..start].to_string(),
+        None => {
+            let end = part
+                .as_ref()
+                .map(|c| c.get(0).unwrap().start())
+                .into_iter()
+                .chain(QUALITY_TAG.find(&text).map(|m| m.start()))
+                .min()
+                .unwrap_or(text.len());
+            text[..end].to_string()
+        }
+    };
+    title = BRACKETED.replace_all(&title, " ").into_owned();
+    let title = WHITESPACE.replace_all(&title, " ").into_owned();
+
+    MovieInfo {
+        title: clean(&title),
+        year: year.and_then(|(_, _, year)| year),
+        part: part.and_then(|c| number(&c, 1)),
+    }
+}
+
+/// Fields captured by a user parse rule
+#[derive(Debug, Default)]
+pub struct RuleMatch {
+    /// Index of the matching rule in the configured list
+    pub rule: usize,
+    pub show: Option<String>,
+    pub season: Option<usize>,
+    pub episode: Option<usize>,
+    pub title: Option<String>,
+}
+
+/// Compiled user parse rules
+#[derive(Default)]
+pub struct ParseRules {
+    rules: Vec<(usize, Regex, RuleTarget)>,
+}
+
+impl ParseRules {
+    pub fn new(rules: &[ParseRule]) -> Self {
+        let rules = rules
+            .iter()
+            .enumerate()
+            .filter_map(|(index, rule)| {
+                match Regex::new(&rule.pattern) {
+                    Ok(regex) => Some((index, regex, rule.target)),
+                    Err(e) => {
+                        eprintln!("[parser] Ignoring invalid rule #{} '{}': {}", index, rule.pattern, e);
+                        None
+                    }
+                }
+            })
+            .collect();
+
+        Self { rules }
+    }
+
+    /// First rule matching the file name or relative path
+    pub fn find(&self, file_name: &str, relative_path: &str) -> Option<RuleMatch> {
+        self.rules.iter().find_map(|(index, regex, target)| {
+            let text = match target {
+                RuleTarget::FileName => file_name,
+                RuleTarget::Path => relative_path,
+            };
+            let captures = regex.captures(text)?;
+            let named = |name: &str| captures.name(name).and_then(|m| clean(m.as_str()));
+
+            Some(RuleMatch {
+                rule: *index,
+                show: named("show"),
+                season: named("season").and_then(|s| s.parse().ok()),
+                episode: named("episode").and_then(|s| s.parse().ok()),
+                title: named("title"),
+            })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Case {
+        name: &'static str,
+        show: Option<&'static str>,
+        season: Option<usize>,
+        episode: Option<usize>,
+        episode_end: Option<usize>,
+        sub_episode: Option<u32>,
+        version: Option<u32>,
+        special: Option<SpecialKind>,
+    }
+
+    const BASE: Case = Case {
+        name: "",
+        show: None,
+        season: None,
+        episode: None,
+        episode_end: None,
+        sub_episode: None,
+        version: None,
+        special: None,
+    };
+
+    #[test]
+    fn parses_release_names() {
+        let cases = [
+            Case {
+                name: "[Group] Show Name - 01v2 [1080p][ABCD1234]",
+                show: Some("Show Name"),
+                episode: Some(1),
+                version: Some(2),
+                ..BASE
+            },
+            Case { name: "Show - 12.5", show: Some("Show"), episode: Some(12), sub_episode: Some(5), ..BASE },
+            Case { name: "Show 2 - 03", show: Some("Show 2"), episode: Some(3), ..BASE },
+            Case { name: "Show Name - 01", show: Some("Show Name"), episode: Some(1), ..BASE },
+            Case { name: "Show Name Episode 5", show: Some("Show Name"), episode: Some(5), ..BASE },
+            Case { name: "Show Name Ep 10", show: Some("Show Name"), episode: Some(10), ..BASE },
+            Case { name: "Show Name 07", show: Some("Show Name"), episode: Some(7), ..BASE },
+            Case {
+                name: "[Group] Show - 01-02 [1080p]",
+                show: Some("Show"),
+                episode: Some(1),
+                episode_end: Some(2),
+                ..BASE
+            },
+            Case {
+                name: "[Group] Show - OVA [720p]",
+                show: Some("Show"),
+                special: Some(SpecialKind::Ova),
+                ..BASE
+            },
+            Case {
+                name: "[Group] Show - OVA 2 [720p]",
+                show: Some("Show"),
+                episode: Some(2),
+                special: Some(SpecialKind::Ova),
+                ..BASE
+            },
+            Case { name: "Show OAD", show: Some("Show"), special: Some(SpecialKind::Oad), ..BASE },
+            Case {
+                name: "Show SP2",
+                show: Some("Show"),
+                episode: Some(2),
+                special: Some(SpecialKind::Special),
+                ..BASE
+            },
+            Case { name: "[Group] 作品名 第01話", show: Some("作品名"), episode: Some(1), ..BASE },
+            Case { name: "作品名 第12話「タイトル」", show: Some("作品名"), episode: Some(12), ..BASE },
+            Case {
+                name: "Show.Name.S01E02.Pilot.1080p.WEB-DL.x264",
+                show: Some("Show Name"),
+                season: Some(1),
+                episode: Some(2),
+                ..BASE
+            },
+            Case { name: "Show Name S02E10", show: Some("Show Name"), season: Some(2), episode: Some(10), ..BASE },
+            Case {
+                name: "Show Name S01E01-E02",
+                show: Some("Show Name"),
+                season: Some(1),
+                episode: Some(1),
+                episode_end: Some(2),
+                ..BASE
+            },
+            Case { name: "Show Name - 1x03", show: Some("Show Name"), season: Some(1), episode: Some(3), ..BASE },
+            Case {
+                name: "[Group]_Show_Name_-_04_[BD_1080p_FLAC][0A1B2C3D]",
+                show: Some("Show Name"),
+                episode: Some(4),
+                ..BASE
+            },
+            Case {
+                name: "[Group] Show (2019) - 05 [1080p]",
+                show: Some("Show"),
+                episode: Some(5),
+                ..BASE
+            },
+            Case { name: "[Group] Show [06v3]", show: Some("Show"), episode: Some(6), version: Some(3), ..BASE },
+            Case { name: "Show - 24 END", show: Some("Show"), episode: Some(24), ..BASE },
+            Case { name: "86 - 01", show: Some("86"), episode: Some(1), ..BASE },
+            Case { name: "Movie Title", show: Some("Movie Title"), ..BASE },
+        ];
+
+        for case in cases {
+            let info = parse_release_name(case.name);
+            assert_eq!(info.show.as_deref(), case.show, "show of {:?}", case.name);
+            assert_eq!(info.season, case.season, "season of {:?}", case.name);
+            assert_eq!(info.episode, case.episode, "episode of {:?}", case.name);
+            assert_eq!(info.episode_end, case.episode_end, "episode_end of {:?}", case.name);
+            assert_eq!(info.sub_episode, case.sub_episode, "sub_episode of {:?}", case.name);
+            assert_eq!(info.version, case.version, "version of {:?}", case.name);
+            assert_eq!(info.special, case.special, "special of {:?}", case.name);
+        }
+    }
+
+    #[test]
+    fn parses_movie_names() {
+        let cases = [
+            ("Title (2019)", Some("Title"), Some(2019), None),
+            ("Movie.Name.2019.1080p.BluRay.x264", Some("Movie Name"), Some(2019), None),
+            ("Title (2019) cd1", Some("Title"), Some(2019), Some(1)),
+            ("Title (2019) - Part 2", Some("Title"), Some(2019), Some(2)),
+            ("Long Movie CD2", Some("Long Movie"), None, Some(2)),
+            ("1917 (2019)", Some("1917"), Some(2019), None),
+            ("Blade Runner 2049", Some("Blade Runner 2049"), None, None),
+            ("Deathly Hallows Part 1 (2010)", Some("Deathly Hallows Part 1"), Some(2010), None),
+            ("[Group] Anime Movie (2016) [1080p]", Some("Anime Movie"), Some(2016), None),
+        ];
+
+        for (name, title, year, part) in cases {
+            let info = parse_movie_name(name);
+            assert_eq!(info.title.as_deref(), title, "title of {:?}", name);
+            assert_eq!(info.year, year, "year of {:?}", name);
+            assert_eq!(info.part, part, "part of {:?}", name);
+        }
+    }
+
+    #[test]
+    fn extracts_tags() {
+        let info = parse_release_name("[SubGroup] Show Name - 01v2 [1080p][abcd1234]");
+        assert_eq!(info.group.as_deref(), Some("SubGroup"));
+        assert_eq!(info.resolution.as_deref(), Some("1080p"));
+        assert_eq!(info.crc.as_deref(), Some("ABCD1234"));
+
+        let info = parse_release_name("Show.Name.S01E02.Pilot.720p.HDTV");
+        assert_eq!(info.title.as_deref(), Some("Pilot"));
+        assert_eq!(info.resolution.as_deref(), Some("720p"));
+
+        let info = parse_release_name("Show - 03 - The Title");
+        assert_eq!(info.title.as_deref(), Some("The Title"));
+    }
+}
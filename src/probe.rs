@@ -0,0 +1,166 @@
+use std::{
+    collections::HashMap,
+    path::{ Path, PathBuf },
+    sync::{ Arc, OnceLock },
+    time::{ Duration, Instant },
+};
+
+use serde_json::Value;
+use tokio::{ process::Command, sync::Semaphore, task::JoinSet, time };
+
+use crate::models::{ Episode, MediaInfo, ScanSettings };
+use crate::scan::ScanProgress;
+use crate::video::VideoFile;
+
+/// A broken file can make ffprobe hang; give up on it after this long
+const PROBE_TIMEOUT: Duration = Duration::from_secs(30);
+
+static FFPROBE_AVAILABLE: OnceLock<bool> = OnceLock::new();
+
+/// Carry over media info from the library for files whose fingerprint is unchanged
+pub fn reuse_media_info(files: &mut [VideoFile], shows: &HashMap<String, Vec<Episode>>) {
+    let known: HashMap<&Path, &Episode> = shows
+        .values()
+        .flatten()
+        .filter(|episode| episode.media_info.is_some())
+        .map(|episode| (episode.file_path.as_path(), episode))
+        .collect();
+
+    for file in files {
+        if
+            let Some(episode) = known.get(file.path.as_path()) &&
+            episode.file_size == file.size &&
+            episode.modified == file.modified
+        {
+            file.media_info = episode.media_info.clone();
+        }
+    }
+}
+
+/// Probe every file that has no media info yet, running at most
+/// `settings.probe_workers` ffprobe processes at a time. Files that fail to
+/// probe are left without media info. Returns `false` if cancelled.
+pub async fn probe_missing(
+    files: &mut [VideoFile],
+    settings: &ScanSettings,
+    progress: &ScanProgress
+) -> bool {
+    let pending: Vec<(usize, PathBuf)> = files
+        .iter()
+        .enumerate()
+        .filter(|(_, file)| file.media_info.is_none())
+        .map(|(index, file)| (index, file.path.clone()))
+        .collect();
+
+    if !settings.probe_media || pending.is_empty() {
+        return true;
+    }
+    if !ffprobe_available().await {
+        println!("[probe] ffprobe not found, skipping media probing");
+        return true;
+    }
+
+    let started = Instant::now();
+    let semaphore = Arc::new(Semaphore::new(settings.probe_workers.max(1)));
+    let mut tasks = JoinSet::new();
+    let mut cancelled = false;
+
+    for (index, path) in pending {
+        let Ok(permit) = Arc::clone(&semaphore).acquire_owned().await else {
+            break;
+        };
+        if progress.is_cancelled() {
+            cancelled = true;
+            break;
+        }
+        tasks.spawn(async move {
+            let _permit = permit;
+            (index, probe_file(&path).await)
+        });
+    }
+
+    let mut probed = 0;
+    let mut failed = 0;
+    while let Some(result) = tasks.join_next().await {
+        match result {
+            Ok((index, Ok(info))) => {
+                files[index].media_info = Some(info);
+                probed += 1;
+                progress.file_probed();
+            }
+            Ok((index, Err(e))) => {
+                eprintln!("[probe] {}: {}", files[index].path.display(), e);
+                failed += 1;
+            }
+            Err(e) => {
+                eprintln!("[probe] Probe task failed: {}", e);
+                failed += 1;
+            }
+        }
+    }
+
+    println!(
+        "[probe] Probed {} file(s), {} failed, in {} ms",
+        probed,
+        failed,
+        started.elapsed().as_millis()
+    );
+    !cancelled
+}
+
+pub async fn ffprobe_available() -> bool {
+    if let Some(&available) = FFPROBE_AVAILABLE.get() {
+        return available;
+    }
+
+    let available = Command::new("ffprobe")
+        .arg("-version")
+        .output().await
+        .map(|output| output.status.success())
+        .unwrap_or(false);
+    *FFPROBE_AVAILABLE.get_or_init(|| available)
+}
+
+async fn probe_file(path: &Path) -> Result<MediaInfo, String> {
+    let output = Command::new("ffprobe")
+        .args(["-v", "quiet"])
+        .args(["-print_format", "json"])
+        .args(["-show_format", "-show_streams"])
+        .arg(path.as_os_str())
+        .kill_on_drop(true)
+        .output();
+
+    let output = time
+        ::timeout(PROBE_TIMEOUT, output).await
+        .map_err(|_| "ffprobe timed out".to_string())?
+        .map_err(|e| format!("Failed to run ffprobe: {}", e))?;
+
+    if !output.status.success() {
+        return Err(format!("ffprobe exited with {}", output.status));
+    }
+
+    let json: Value = serde_json
+        ::from_slice(&output.stdout)
+        .map_err(|e| format!("Failed to parse ffprobe output: {}", e))?;
+
+    let streams = json["streams"].as_array().cloned().unwrap_or_default();
+    let codec = |stream: &Value| stream["codec_name"].as_str().map(String::from);
+
+    let video = streams_of_type(&streams, "video").next();
+    Ok(MediaInfo {
+        duration: json["format"]["duration"].as_str().and_then(|d| d.parse().ok()),
+        video_codec: video.and_then(codec),
+        width: video.and_then(|v| v["width"].as_u64()).map(|w| w as u32),
+        height: video.and_then(|v| v["height"].as_u64()).map(|h| h as u32),
+        audio_codec: streams_of_type(&streams, "audio").next().and_then(codec),
+        subtitle_codecs: streams_of_type(&streams, "subtitle").filter_map(codec).collect(),
+    })
+}
+
+fn streams_of_type<'a>(streams: &'a [Value], kind: &'a str) -> impl Iterator<Item = &'a Value> {
+    streams
+        .iter()
+        .filter(move |stream| stream["codec_type"] == kind)
+        // Embedded cover art shows up as a video stream
+        .filter(|stream| stream["disposition"]["attached_pic"] != 1)
+}
@@ -0,0 +1,283 @@
+use std::{
+    path::{ Path, PathBuf },
+    sync::{ atomic::{ AtomicBool, AtomicU64, AtomicUsize, Ordering }, Arc, Mutex },
+    time::{ Duration, Instant },
+};
+
+use axum::http::StatusCode;
+use serde::Serialize;
+
+use crate::config::save_config;
+use crate::cursors::update_cursors;
+use crate::metadata::update_show_metadata;
+use crate::models::{ AppState, LibraryRoot };
+use crate::parser::ParseRules;
+use crate::probe::{ probe_missing, reuse_media_info };
+use crate::video::{ available_files, merge_scan, scan_for_videos, ScanDiff };
+
+/// Finished jobs kept around so clients can still fetch their result
+const MAX_FINISHED_JOBS: usize = 20;
+
+static NEXT_JOB_ID: AtomicU64 = AtomicU64::new(1);
+
+#[derive(Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ScanJobState {
+    Running,
+    Completed,
+    Cancelled,
+    Failed,
+}
+
+/// Live counters updated by the directory walk
+#[derive(Default)]
+pub struct ScanProgress {
+    current_dir: Mutex<PathBuf>,
+    files_seen: AtomicUsize,
+    videos_accepted: AtomicUsize,
+    files_probed: AtomicUsize,
+    cancelled: AtomicBool,
+}
+
+impl ScanProgress {
+    pub fn enter_dir(&self, dir: &Path) {
+        *self.current_dir.lock().unwrap() = dir.to_path_buf();
+    }
+
+    pub fn file_seen(&self) {
+        self.files_seen.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn video_accepted(&self) {
+        self.videos_accepted.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn file_probed(&self) {
+        self.files_probed.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn files_seen(&self) -> usize {
+        self.files_seen.load(Ordering::Relaxed)
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+}
+
+struct ScanOutcome {
+    state: ScanJobState,
+    elapsed: Option<Duration>,
+    error: Option<String>,
+    diff: Option<ScanDiff>,
+}
+
+pub struct ScanJob {
+    pub id: String,
+    pub libraries: Vec<LibraryRoot>,
+    started: Instant,
+    pub progress: Arc<ScanProgress>,
+    outcome: Mutex<ScanOutcome>,
+}
+
+#[derive(Serialize)]
+pub struct ScanJobStatus {
+    pub id: String,
+    pub libraries: Vec<String>,
+    pub state: ScanJobState,
+    pub current_dir: String,
+    pub files_seen: usize,
+    pub videos_accepted: usize,
+    pub files_probed: usize,
+    pub elapsed_ms: u128,
+    pub error: Option<String>,
+    pub diff: Option<ScanDiff>,
+}
+
+impl ScanJob {
+    fn new(libraries: Vec<LibraryRoot>) -> Self {
+        Self {
+            id: format!("scan-{}", NEXT_JOB_ID.fetch_add(1, Ordering::Relaxed)),
+            libraries,
+            started: Instant::now(),
+            progress: Arc::new(ScanProgress::default()),
+            outcome: Mutex::new(ScanOutcome {
+                state: ScanJobState::Running,
+                elapsed: None,
+                error: None,
+                diff: None,
+            }),
+        }
+    }
+
+    pub fn state(&self) -> ScanJobState {
+        self.outcome.lock().unwrap().state
+    }
+
+    pub fn cancel(&self) {
+        self.progress.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    fn finish(&self, state: ScanJobState, error: Option<String>, diff: Option<ScanDiff>) {
+        let mut outcome = self.outcome.lock().unwrap();
+        outcome.state = state;
+        outcome.elapsed = Some(self.started.elapsed());
+        outcome.error = error;
+        outcome.diff = diff;
+    }
+
+    pub fn status(&self) -> ScanJobStatus {
+        let outcome = self.outcome.lock().unwrap();
+        ScanJobStatus {
+            id: self.id.clone(),
+            libraries: self.libraries
+                .iter()
+                .map(|root| root.id.clone())
+                .collect(),
+            state: outcome.state,
+            current_dir: self.progress.current_dir.lock().unwrap().display().to_string(),
+            files_seen: self.progress.files_seen(),
+            videos_accepted: self.progress.videos_accepted.load(Ordering::Relaxed),
+            files_probed: self.progress.files_probed.load(Ordering::Relaxed),
+            elapsed_ms: outcome.elapsed.unwrap_or_else(|| self.started.elapsed()).as_millis(),
+            error: outcome.error.clone(),
+            diff: outcome.diff.clone(),
+        }
+    }
+}
+
+/// Start a background scan of the given library roots (all enabled roots if
+/// `only` is `None`). A running job that already covers them is returned as-is.
+pub async fn start_scan_job(
+    state: Arc<AppState>,
+    only: Option<&str>
+) -> Result<Arc<ScanJob>, (StatusCode, String)> {
+    let libraries: Vec<LibraryRoot> = state.libraries
+        .read().await
+        .iter()
+        .filter(|root| only.map_or(root.enabled, |id| root.id == id))
+        .cloned()
+        .collect();
+
+    if libraries.is_empty() {
+        return Err(
+            match only {
+                Some(id) => (StatusCode::NOT_FOUND, format!("Library '{}' not found", id)),
+                None => (StatusCode::BAD_REQUEST, "No library folders configured".to_string()),
+            }
+        );
+    }
+
+    let mut jobs = state.scan_jobs.write().await;
+    if let Some(running) = jobs.values().find(|job| job.state() == ScanJobState::Running) {
+        let covered = libraries
+            .iter()
+            .all(|root| running.libraries.iter().any(|r| r.id == root.id));
+        if covered {
+            return Ok(Arc::clone(running));
+        }
+        return Err((StatusCode::CONFLICT, format!("Scan job {} is already running", running.id)));
+    }
+
+    prune_finished_jobs(&mut jobs);
+
+    let job = Arc::new(ScanJob::new(libraries));
+    jobs.insert(job.id.clone(), Arc::clone(&job));
+    drop(jobs);
+
+    println!("[scan] Started job {} for {} library root(s)", job.id, job.libraries.len());
+
+    let job_clone = Arc::clone(&job);
+    tokio::spawn(async move {
+        run_scan_job(state, job_clone).await;
+    });
+
+    Ok(job)
+}
+
+async fn run_scan_job(state: Arc<AppState>, job: Arc<ScanJob>) {
+    let mut total = ScanDiff::default();
+    let settings = state.scan_settings.read().await.clone();
+
+    for root in &job.libraries {
+        let mut video_files = match scan_for_videos(root, &settings, Arc::clone(&job.progress)).await {
+            Ok(Some(files)) => files,
+            Ok(None) => {
+                finish_cancelled(&state, &job, total).await;
+                return;
+            }
+            Err(e) => {
+                eprintln!("[scan] Job {} failed: {}", job.id, e);
+                job.finish(ScanJobState::Failed, Some(e), Some(total));
+                return;
+            }
+        };
+
+        reuse_media_info(&mut video_files, &*state.shows.read().await);
+        if !probe_missing(&mut video_files, &settings, &job.progress).await {
+            finish_cancelled(&state, &job, total).await;
+            return;
+        }
+
+        // The root may have been removed while it was being walked
+        if !state.libraries.read().await.iter().any(|r| r.id == root.id) {
+            continue;
+        }
+
+        let rules = ParseRules::new(&state.parse_rules.read().await);
+        let overrides = state.overrides.read().await.clone();
+        let mut shows = state.shows.write().await;
+        let (merged, diff) = merge_scan(
+            &shows,
+            root,
+            &video_files,
+            &rules,
+            &overrides,
+            |episode| episode.library_id == root.id
+        );
+        *shows = merged;
+        *state.tv_files.write().await = available_files(&shows);
+        update_cursors(&state, &shows).await;
+        *state.library_stats.write().await = None;
+        update_show_metadata(&mut *state.show_metadata.write().await, &shows, &video_files);
+        drop(shows);
+
+        println!("[scan] Library '{}' scanned: {}", root.id, diff.summary());
+        total.extend(diff);
+    }
+
+    println!("[scan] Job {} finished: {}", job.id, total.summary());
+
+    match save_config(&state).await {
+        Ok(()) => job.finish(ScanJobState::Completed, None, Some(total)),
+        Err(e) => {
+            job.finish(ScanJobState::Failed, Some(format!("Failed to save config: {}", e)), Some(total))
+        }
+    }
+}
+
+async fn finish_cancelled(state: &AppState, job: &ScanJob, total: ScanDiff) {
+    println!("[scan] Job {} cancelled", job.id);
+    // Roots finished before the cancellation have already been merged
+    if !total.is_empty() && let Err(e) = save_config(state).await {
+        eprintln!("[scan] Failed to save config: {}", e);
+    }
+    job.finish(ScanJobState::Cancelled, None, Some(total));
+}
+
+fn prune_finished_jobs(jobs: &mut std::collections::HashMap<String, Arc<ScanJob>>) {
+    let mut finished: Vec<(Instant, String)> = jobs
+        .values()
+        .filter(|job| job.state() != ScanJobState::Running)
+        .map(|job| (job.started, job.id.clone()))
+        .collect();
+
+    if finished.len() < MAX_FINISHED_JOBS {
+        return;
+    }
+
+    finished.sort();
+    for (_, id) in finished.iter().take(finished.len() + 1 - MAX_FINISHED_JOBS) {
+        jobs.remove(id);
+    }
+}
@@ -0,0 +1,274 @@
+use std::{ collections::{ HashMap, HashSet }, path::PathBuf };
+
+use serde::{ Deserialize, Serialize };
+
+use crate::models::{ Episode, MediaMetadata, SpecialKind };
+
+const DEFAULT_PAGE_SIZE: usize = 50;
+const MAX_PAGE_SIZE: usize = 500;
+
+#[derive(Deserialize, Serialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SearchSort {
+    /// Show name, then episode order
+    #[default]
+    Show,
+    Name,
+    Year,
+    Modified,
+    Size,
+}
+
+#[derive(Deserialize, Serialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SortOrder {
+    #[default]
+    Asc,
+    Desc,
+}
+
+#[derive(Deserialize, Default)]
+pub struct SearchQuery {
+    /// Words that must all appear in the show name, episode name, title or file name
+    pub q: Option<String>,
+    pub show: Option<String>,
+    pub season: Option<usize>,
+    pub genre: Option<String>,
+    pub library: Option<String>,
+    pub watched: Option<bool>,
+    #[serde(default)]
+    pub include_hidden: bool,
+    #[serde(default)]
+    pub include_unavailable: bool,
+    #[serde(default)]
+    pub sort: SearchSort,
+    #[serde(default)]
+    pub order: SortOrder,
+    pub limit: Option<usize>,
+    /// `next_cursor` from the previous page
+    pub cursor: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct SearchItem {
+    pub file_id: String,
+    pub show_name: String,
+    pub name: String,
+    pub title: Option<String>,
+    pub season: Option<usize>,
+    pub episode_number: Option<usize>,
+    pub episode_end: Option<usize>,
+    pub special: Option<SpecialKind>,
+    pub year: Option<u16>,
+    pub library_id: String,
+    pub file_path: String,
+    pub available: bool,
+    pub hidden: bool,
+    pub watched: bool,
+    /// Length in seconds, once probed
+    pub duration: Option<f64>,
+}
+
+#[derive(Serialize)]
+pub struct SearchPage {
+    pub items: Vec<SearchItem>,
+    /// Number of episodes matching the filters across all pages
+    pub total: usize,
+    /// Pass as `cursor` to fetch the next page; `None` on the last page
+    pub next_cursor: Option<String>,
+}
+
+/// Position of an episode in a sort order. Later fields break ties, down to
+/// the file path, so every episode has a distinct key and a cursor still
+/// points to the right place after the library changes.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, PartialOrd, Ord)]
+struct SortKey {
+    primary: Option<PrimaryKey>,
+    show: String,
+    season: usize,
+    /// Unnumbered episodes sort after numbered ones
+    episode: (bool, usize),
+    sub_episode: Option<u32>,
+    name: String,
+    path: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, PartialOrd, Ord)]
+enum PrimaryKey {
+    Text(String),
+    Number(u64),
+}
+
+#[derive(Serialize, Deserialize)]
+struct Cursor {
+    sort: SearchSort,
+    order: SortOrder,
+    after: SortKey,
+}
+
+impl SortKey {
+    fn new(episode: &Episode, sort: SearchSort) -> Self {
+        let primary = match sort {
+            SearchSort::Show => None,
+            SearchSort::Name => Some(PrimaryKey::Text(episode.name.to_lowercase())),
+            SearchSort::Year => Some(PrimaryKey::Number(episode.year.unwrap_or(0).into())),
+            SearchSort::Modified => Some(PrimaryKey::Number(episode.modified.unwrap_or(0))),
+            SearchSort::Size => Some(PrimaryKey::Number(episode.file_size)),
+        };
+
+        Self {
+            primary,
+            show: episode.show_name.to_lowercase(),
+            season: episode.season.unwrap_or(1),
+            episode: (episode.episode_number.is_none(), episode.episode_number.unwrap_or(0)),
+            sub_episode: episode.sub_episode,
+            name: episode.name.clone(),
+            path: episode.file_path.display().to_string(),
+        }
+    }
+}
+
+/// Filter, sort and page through the library. Only the requested page is
+/// turned into response items.
+pub fn search_library(
+    shows: &HashMap<String, Vec<Episode>>,
+    show_metadata: &HashMap<String, MediaMetadata>,
+    aired: &HashSet<PathBuf>,
+    query: &SearchQuery
+) -> Result<SearchPage, String> {
+    let limit = query.limit.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE);
+    let after = match &query.cursor {
+        Some(cursor) => Some(decode_cursor(cursor, query)?),
+        None => None,
+    };
+
+    let terms: Vec<String> = query.q
+        .as_deref()
+        .unwrap_or("")
+        .split_whitespace()
+        .map(str::to_lowercase)
+        .collect();
+    let is_watched = |episode: &Episode| aired.contains(&episode.file_path);
+
+    let has_genre = |episode: &Episode, wanted: &str| {
+        show_metadata
+            .get(&episode.show_name)
+            .into_iter()
+            .chain(episode.metadata.as_ref())
+            .flat_map(|metadata| metadata.genres.iter())
+            .any(|genre| genre.eq_ignore_ascii_case(wanted))
+    };
+
+    let matches = |episode: &Episode| {
+        (query.include_hidden || !episode.hidden) &&
+            (query.include_unavailable || episode.available) &&
+            query.show.as_ref().is_none_or(|show| episode.show_name.eq_ignore_ascii_case(show)) &&
+            query.season.is_none_or(|season| episode.season.unwrap_or(1) == season) &&
+            query.library.as_ref().is_none_or(|id| &episode.library_id == id) &&
+            query.genre.as_ref().is_none_or(|genre| has_genre(episode, genre)) &&
+            query.watched.is_none_or(|watched| is_watched(episode) == watched) &&
+            matches_terms(episode, &terms)
+    };
+
+    let mut total = 0;
+    let mut page: Vec<(SortKey, &Episode)> = Vec::new();
+    for episode in shows.values().flatten() {
+        if !matches(episode) {
+            continue;
+        }
+        total += 1;
+
+        let key = SortKey::new(episode, query.sort);
+        let past_cursor = after.as_ref().is_none_or(|after| {
+            match query.order {
+                SortOrder::Asc => key > *after,
+                SortOrder::Desc => key < *after,
+            }
+        });
+        if past_cursor {
+            page.push((key, episode));
+        }
+    }
+
+    match query.order {
+        SortOrder::Asc => page.sort_unstable_by(|a, b| a.0.cmp(&b.0)),
+        SortOrder::Desc => page.sort_unstable_by(|a, b| b.0.cmp(&a.0)),
+    }
+
+    let has_more = page.len() > limit;
+    page.truncate(limit);
+
+    let next_cursor = match page.last() {
+        Some((key, _)) if has_more => Some(encode_cursor(query, key)?),
+        _ => None,
+    };
+
+    let items = page
+        .into_iter()
+        .map(|(_, episode)| SearchItem {
+            file_id: episode.file_id.clone(),
+            show_name: episode.show_name.clone(),
+            name: episode.name.clone(),
+            title: episode.title.clone(),
+            season: episode.season,
+            episode_number: episode.episode_number,
+            episode_end: episode.episode_end,
+            special: episode.special,
+            year: episode.year,
+            library_id: episode.library_id.clone(),
+            file_path: episode.file_path.display().to_string(),
+            available: episode.available,
+            hidden: episode.hidden,
+            watched: is_watched(episode),
+            duration: episode.media_info.as_ref().and_then(|info| info.duration),
+        })
+        .collect();
+
+    Ok(SearchPage { items, total, next_cursor })
+}
+
+fn matches_terms(episode: &Episode, terms: &[String]) -> bool {
+    if terms.is_empty() {
+        return true;
+    }
+
+    let file_name = episode.file_path
+        .file_name()
+        .map(|name| name.to_string_lossy())
+        .unwrap_or_default();
+    let haystack = format!(
+        "{}\n{}\n{}\n{}",
+        episode.show_name,
+        episode.name,
+        episode.title.as_deref().unwrap_or(""),
+        file_name
+    ).to_lowercase();
+
+    terms.iter().all(|term| haystack.contains(term.as_str()))
+}
+
+/// Cursors are hex-encoded JSON so they survive query strings untouched
+fn encode_cursor(query: &SearchQuery, key: &SortKey) -> Result<String, String> {
+    let cursor = Cursor { sort: query.sort, order: query.order, after: key.clone() };
+    let json = serde_json::to_vec(&cursor).map_err(|e| format!("Failed to encode cursor: {}", e))?;
+    Ok(
+        json
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect()
+    )
+}
+
+fn decode_cursor(cursor: &str, query: &SearchQuery) -> Result<SortKey, String> {
+    let bytes = (0..cursor.len())
+        .step_by(2)
+        .map(|i| cursor.get(i..i + 2).and_then(|pair| u8::from_str_radix(pair, 16).ok()))
+        .collect::<Option<Vec<u8>>>()
+        .ok_or_else(|| "Invalid cursor".to_string())?;
+    let cursor: Cursor = serde_json::from_slice(&bytes).map_err(|_| "Invalid cursor".to_string())?;
+
+    if cursor.sort != query.sort || cursor.order != query.order {
+        return Err("Cursor was created for a different sort order".to_string());
+    }
+    Ok(cursor.after)
+}
@@ -0,0 +1,136 @@
+use std::{ collections::{ BTreeMap, HashMap }, sync::Arc, time::{ SystemTime, UNIX_EPOCH } };
+
+use serde::Serialize;
+
+use crate::models::{ AppState, Episode, MediaInfo };
+use crate::video::is_audio_file;
+
+/// The TV channel always encodes to this; files already in this format are
+/// candidates for passthrough instead of a full re-encode
+const OUTPUT_VIDEO_CODEC: &str = "h264";
+const OUTPUT_AUDIO_CODEC: &str = "aac";
+const OUTPUT_SIZE: (u32, u32) = (1920, 1080);
+
+const RESOLUTIONS: [(u32, u32, &str); 5] = [
+    (3840, 2160, "2160p"),
+    (2560, 1440, "1440p"),
+    (1920, 1080, "1080p"),
+    (1280, 720, "720p"),
+    (720, 480, "480p"),
+];
+
+#[derive(Serialize, Default)]
+pub struct LibraryTotals {
+    /// Files currently on disk
+    pub episodes: usize,
+    /// Files still in the library but gone from disk
+    pub missing: usize,
+    /// Summed duration of probed files, in seconds
+    pub runtime_seconds: f64,
+    pub disk_usage_bytes: u64,
+    pub probed: usize,
+    pub transcode: usize,
+    pub passthrough: usize,
+    pub video_codecs: BTreeMap<String, usize>,
+    pub audio_codecs: BTreeMap<String, usize>,
+    pub resolutions: BTreeMap<String, usize>,
+}
+
+#[derive(Serialize)]
+pub struct LibraryStats {
+    /// Seconds since the Unix epoch
+    pub generated_at: u64,
+    pub overall: LibraryTotals,
+    pub shows: BTreeMap<String, LibraryTotals>,
+}
+
+impl LibraryTotals {
+    fn add(&mut self, episode: &Episode) {
+        if !episode.available {
+            self.missing += 1;
+            return;
+        }
+
+        self.episodes += 1;
+        self.disk_usage_bytes += episode.file_size;
+
+        let Some(info) = &episode.media_info else {
+            return;
+        };
+        self.probed += 1;
+        self.runtime_seconds += info.duration.unwrap_or(0.0);
+
+        if let Some(codec) = &info.video_codec {
+            *self.video_codecs.entry(codec.clone()).or_default() += 1;
+        }
+        if let Some(codec) = &info.audio_codec {
+            *self.audio_codecs.entry(codec.clone()).or_default() += 1;
+        }
+        if let Some(label) = resolution_label(info) {
+            *self.resolutions.entry(label.to_string()).or_default() += 1;
+        }
+
+        if !is_audio_file(&episode.file_path) && matches_output(info) {
+            self.passthrough += 1;
+        } else {
+            self.transcode += 1;
+        }
+    }
+}
+
+/// Cached statistics, recomputed after the library changed
+pub async fn library_stats(state: &AppState) -> Arc<LibraryStats> {
+    if let Some(stats) = state.library_stats.read().await.as_ref() {
+        return Arc::clone(stats);
+    }
+
+    let shows = state.shows.read().await;
+    let mut cached = state.library_stats.write().await;
+    // Another request may have filled the cache while we waited for the lock
+    if let Some(stats) = cached.as_ref() {
+        return Arc::clone(stats);
+    }
+
+    let stats = Arc::new(compute_library_stats(&shows));
+    *cached = Some(Arc::clone(&stats));
+    stats
+}
+
+fn compute_library_stats(shows: &HashMap<String, Vec<Episode>>) -> LibraryStats {
+    let mut overall = LibraryTotals::default();
+    let mut per_show = BTreeMap::new();
+
+    for (name, episodes) in shows {
+        let mut totals = LibraryTotals::default();
+        for episode in episodes {
+            totals.add(episode);
+            overall.add(episode);
+        }
+        per_show.insert(name.clone(), totals);
+    }
+
+    LibraryStats {
+        generated_at: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0),
+        overall,
+        shows: per_show,
+    }
+}
+
+fn resolution_label(info: &MediaInfo) -> Option<&'static str> {
+    let (width, height) = (info.width?, info.height?);
+    // Either side counts so cropped widescreen (1920x800) still lands in 1080p
+    Some(
+        RESOLUTIONS.iter()
+            .find(|(w, h, _)| width >= *w || height >= *h)
+            .map_or("SD", |(_, _, label)| *label)
+    )
+}
+
+fn matches_output(info: &MediaInfo) -> bool {
+    info.video_codec.as_deref() == Some(OUTPUT_VIDEO_CODEC) &&
+        (info.width, info.height) == (Some(OUTPUT_SIZE.0), Some(OUTPUT_SIZE.1)) &&
+        info.audio_codec.as_deref().is_none_or(|codec| codec == OUTPUT_AUDIO_CODEC)
+}
@@ -1,10 +1,77 @@
-use std::{ path::{ Path, PathBuf }, process::Stdio, sync::{ Arc, OnceLock }, time::Duration };
+use std::{
+    mem,
+    path::Path,
+    process::Stdio,
+    sync::{ Arc, OnceLock },
+    time::{ Duration, SystemTime, UNIX_EPOCH },
+};
+
+use tokio::{
+    fs,
+    io::{ AsyncBufReadExt, AsyncWriteExt, BufReader },
+    process::{ Child, ChildStdin, Command },
+    sync::mpsc::{ self, UnboundedReceiver },
+    time,
+};
+
+use crate::channel::Rotation;
+use crate::config::save_cursors;
+use crate::cursors::{
+    aired_files,
+    forget_aired,
+    interrupted_airing,
+    record_airing,
+    record_offset,
+    TV_CHANNEL,
+};
+use crate::health::broken_files;
+use crate::models::{
+    AppState,
+    ChannelCommand,
+    ChannelRotation,
+    Episode,
+    NowPlaying,
+    PlayMode,
+    PlaylistEndPolicy,
+    PlaylistItem,
+    ScheduledEpisode,
+    SubtitleMode,
+};
+use crate::video::is_audio_file;
 
-use tokio::{ fs, process::Command, time };
+static FFMPEG_AVAILABLE: OnceLock<bool> = OnceLock::new();
 
-use crate::models::{ AppState, SubtitleMode };
+/// Upcoming episodes listed in the now/next data
+const NEXT_UP_LIMIT: usize = 10;
+
+/// How often the position of the running episode is saved
+const PROGRESS_SAVE_INTERVAL: Duration = Duration::from_secs(5);
+
+/// How long ffmpeg gets to quit on its own before it is killed
+const FFMPEG_STOP_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// An encode on the channel: its position is followed as it goes and
+/// transport commands interrupt it
+struct ChannelEncode<'a> {
+    state: &'a AppState,
+    /// Episode whose show cursor gets the position; queued files leave cursors alone
+    tracked: Option<&'a Episode>,
+    /// Offset the encode started at, in seconds
+    start: f64,
+    /// Position reached so far, in seconds
+    position: f64,
+    /// Commands for this encode only, so none left over can reach a later one
+    commands: UnboundedReceiver<ChannelCommand>,
+}
 
-static FFMPEG_AVAILABLE: OnceLock<bool> = OnceLock::new();
+impl<'a> ChannelEncode<'a> {
+    /// Starts taking the channel's commands, until `encode_file` returns
+    async fn new(state: &'a AppState, tracked: Option<&'a Episode>, start: f64) -> Self {
+        let (sender, commands) = mpsc::unbounded_channel();
+        *state.channel_commands.write().await = Some(sender);
+        Self { state, tracked, start, position: start, commands }
+    }
+}
 
 /// Generate a test card stream
 async fn stream_test_card(out_dir: &Path) -> Result<(), String> {
@@ -51,66 +118,31 @@ async fn stream_test_card(out_dir: &Path) -> Result<(), String> {
     }
 }
 
-/// Play a specific file
-pub async fn play_file(state: Arc<AppState>, file_path: PathBuf) -> Result<(), String> {
-    println!("[streaming] Playing file: {}", file_path.display());
+/// Add a file to the play-next queue. With `now` it goes to the front and
+/// interrupts what the channel is playing, which continues afterwards.
+pub async fn enqueue(state: Arc<AppState>, entry: ScheduledEpisode, now: bool) {
+    println!("[tv] Queued {}{}", entry.file_path.display(), if now { " to play now" } else { "" });
 
-    if !file_path.exists() {
-        return Err(format!("File does not exist: {}", file_path.display()));
+    {
+        let mut queue = state.play_queue.write().await;
+        if now {
+            queue.push_front(entry);
+        } else {
+            queue.push_back(entry);
+        }
     }
 
-    check_ffmpeg_availability().await?;
-
-    let _out_dir = state.hls_root.join("tv");
-    let _subtitle_mode = state.subtitle_mode.read().await.clone();
-
-    // update state
-    *state.current_playing.write().await = Some(file_path.clone());
-    *state.is_playing.write().await = true;
-
-    stop_streaming(state.clone()).await;
-
-    let state_clone = state.clone();
-    let handle = tokio::spawn(async move {
-        loop {
-            let file = state_clone.current_playing.read().await.clone();
-            let is_playing = *state_clone.is_playing.read().await;
-            let subtitle_mode = state_clone.subtitle_mode.read().await.clone();
-
-            if !is_playing {
-                println!("[streaming] Playback stopped");
-                break;
-            }
-
-            if let Some(ref file_path) = file {
-                if file_path.exists() {
-                    match
-                        process_video_file(
-                            file_path,
-                            &state_clone.hls_root.join("tv"),
-                            &subtitle_mode
-                        ).await
-                    {
-                        Ok(_) => {
-                            println!("[streaming] File completed, looping...");
-                        }
-                        Err(e) => {
-                            println!("[streaming] Error playing file: {}", e);
-                            time::sleep(Duration::from_secs(2)).await;
-                        }
-                    }
-                } else {
-                    println!("[streaming] File no longer exists");
-                    break;
-                }
-            } else {
-                break;
-            }
-        }
-    });
+    // Only a running encode listens for commands; otherwise the queue is
+    // picked up after the current file
+    if
+        now &&
+        state.now_playing.read().await.is_some() &&
+        let Some(sender) = state.channel_commands.read().await.as_ref()
+    {
+        let _ = sender.send(ChannelCommand::PlayQueued);
+    }
 
-    state.jobs.write().await.insert("tv".into(), handle);
-    Ok(())
+    start_tv_loop_if_needed(state).await;
 }
 
 pub async fn stop_streaming(state: Arc<AppState>) {
@@ -118,6 +150,8 @@ pub async fn stop_streaming(state: Arc<AppState>) {
 
     *state.is_playing.write().await = false;
     *state.current_playing.write().await = None;
+    *state.now_playing.write().await = None;
+    *state.channel_commands.write().await = None;
 
     // Cancel existing job
     let mut jobs = state.jobs.write().await;
@@ -175,16 +209,7 @@ async fn detect_subtitle_format(input_path: &Path) -> Result<bool, String> {
     Ok(true)
 }
 
-async fn build_ffmpeg_command(
-    input_path: &Path,
-    output_dir: &Path,
-    subtitle_mode: &SubtitleMode
-) -> Command {
-    let mut cmd = Command::new("ffmpeg");
-    let seg_tmpl = output_dir.join("%09d.ts");
-
-    cmd.arg("-re").arg("-i").arg(input_path.as_os_str());
-
+async fn map_video_streams(cmd: &mut Command, input_path: &Path, subtitle_mode: &SubtitleMode) {
     match subtitle_mode {
         SubtitleMode::None => {
             cmd.args(["-map", "0:v:0"])
@@ -218,6 +243,34 @@ async fn build_ffmpeg_command(
             }
         }
     }
+}
+
+/// `start` seeks into the input, in seconds
+async fn build_ffmpeg_command(
+    input_path: &Path,
+    output_dir: &Path,
+    subtitle_mode: &SubtitleMode,
+    start: f64
+) -> Command {
+    let mut cmd = Command::new("ffmpeg");
+    let seg_tmpl = output_dir.join("%09d.ts");
+
+    cmd.arg("-re");
+    if start > 0.0 {
+        cmd.arg("-ss").arg(format!("{:.3}", start));
+    }
+    cmd.arg("-i").arg(input_path.as_os_str());
+
+    if is_audio_file(input_path) {
+        // Music has no picture, pair it with a black frame so the HLS output stays uniform
+        cmd.args(["-f", "lavfi"])
+            .args(["-i", "color=c=black:size=1920x1080:rate=30"])
+            .args(["-map", "1:v:0"])
+            .args(["-map", "0:a:0"])
+            .arg("-shortest");
+    } else {
+        map_video_streams(&mut cmd, input_path, subtitle_mode).await;
+    }
 
     cmd.args(["-c:v", "libx264", "-preset", "veryfast"])
         .args(["-s", "1920x1080"])
@@ -231,15 +284,20 @@ async fn build_ffmpeg_command(
             "append_list+delete_segments+program_date_time+omit_endlist+independent_segments",
         ])
         .args(["-hls_segment_filename", &seg_tmpl.to_string_lossy()])
+        // Progress goes to stdout, where `execute_ffmpeg_streaming` reads the position
+        .args(["-progress", "pipe:1"])
         .arg(output_dir.join("index.m3u8"))
-        .stdin(Stdio::null())
-        .stdout(Stdio::inherit())
-        .stderr(Stdio::inherit());
+        // Taken by `execute_ffmpeg_streaming` to ask ffmpeg to quit
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::inherit())
+        // Stopping the channel aborts its task; the encode has to end with it
+        .kill_on_drop(true);
 
     cmd
 }
 
-async fn check_ffmpeg_availability() -> Result<(), String> {
+pub async fn check_ffmpeg_availability() -> Result<(), String> {
     // Check cache first
     if let Some(&available) = FFMPEG_AVAILABLE.get() {
         return if available {
@@ -252,21 +310,27 @@ async fn check_ffmpeg_availability() -> Result<(), String> {
     match Command::new("ffmpeg").arg("-version").output().await {
         Ok(output) => {
             if output.status.success() {
-                FFMPEG_AVAILABLE.set(true).unwrap();
+                let _ = FFMPEG_AVAILABLE.set(true);
                 Ok(())
             } else {
-                FFMPEG_AVAILABLE.set(false).unwrap();
+                let _ = FFMPEG_AVAILABLE.set(false);
                 Err(format!("FFmpeg version check failed with status: {}", output.status))
             }
         }
         Err(e) => {
-            FFMPEG_AVAILABLE.set(false).unwrap();
+            let _ = FFMPEG_AVAILABLE.set(false);
             Err(format!("FFmpeg not found or not accessible: {}", e))
         }
     }
 }
 
-async fn execute_ffmpeg_streaming(cmd: &mut Command, file_path: &Path) -> Result<(), String> {
+/// Run ffmpeg to the end, or until a transport command interrupts it, in
+/// which case the command is returned
+async fn execute_ffmpeg_streaming(
+    cmd: &mut Command,
+    file_path: &Path,
+    mut encode: Option<&mut ChannelEncode<'_>>
+) -> Result<Option<ChannelCommand>, String> {
     println!("[streaming] Starting FFmpeg process...");
     println!("[streaming] Working directory: {:?}", std::env::current_dir());
     println!("[streaming] FFmpeg command: {:?}", cmd);
@@ -276,11 +340,49 @@ async fn execute_ffmpeg_streaming(cmd: &mut Command, file_path: &Path) -> Result
             let pid = child.id().unwrap_or(0);
             println!("[streaming] FFmpeg process started successfully (PID: {})", pid);
 
+            let mut stdin = child.stdin.take();
+            // Drain the progress output even when nothing is tracked, so ffmpeg never blocks on it
+            if let Some(stdout) = child.stdout.take() {
+                let mut lines = BufReader::new(stdout).lines();
+                let mut last_saved = time::Instant::now();
+                loop {
+                    let line = match encode.as_deref_mut() {
+                        Some(encode) =>
+                            tokio::select! {
+                                line = lines.next_line() => line,
+                                Some(command) = encode.commands.recv() => {
+                                    println!("[streaming] Stopping FFmpeg for {:?}", command);
+                                    stop_ffmpeg(&mut child, stdin.take()).await;
+                                    return Ok(Some(command));
+                                }
+                            },
+                        None => lines.next_line().await,
+                    };
+                    let Ok(Some(line)) = line else {
+                        break;
+                    };
+                    let Some(encode) = encode.as_deref_mut() else {
+                        continue;
+                    };
+                    let Some(out_time) = parse_out_time(&line) else {
+                        continue;
+                    };
+                    encode.position = encode.start + out_time;
+                    if
+                        let Some(episode) = encode.tracked &&
+                        last_saved.elapsed() >= PROGRESS_SAVE_INTERVAL
+                    {
+                        record_offset(encode.state, TV_CHANNEL, episode, encode.position).await;
+                        last_saved = time::Instant::now();
+                    }
+                }
+            }
+
             match child.wait().await {
                 Ok(status) => {
                     if status.success() {
                         println!("[streaming] FFmpeg process completed successfully");
-                        Ok(())
+                        Ok(None)
                     } else {
                         Err(format!("FFmpeg exited with error status: {}", status))
                     }
@@ -292,6 +394,24 @@ async fn execute_ffmpeg_streaming(cmd: &mut Command, file_path: &Path) -> Result
     }
 }
 
+/// Ask ffmpeg to quit with `q`, so it finishes the segment and playlist it is
+/// writing, and kill it if it takes too long
+async fn stop_ffmpeg(child: &mut Child, stdin: Option<ChildStdin>) {
+    if let Some(mut stdin) = stdin {
+        let _ = stdin.write_all(b"q").await;
+    }
+    if time::timeout(FFMPEG_STOP_TIMEOUT, child.wait()).await.is_err() {
+        println!("[streaming] FFmpeg did not quit in time, killing it");
+        let _ = child.kill().await;
+    }
+}
+
+/// Output position in seconds from an `out_time_us=` line of `-progress` output
+fn parse_out_time(line: &str) -> Option<f64> {
+    let micros: i64 = line.strip_prefix("out_time_us=")?.trim().parse().ok()?;
+    Some((micros.max(0) as f64) / 1_000_000.0)
+}
+
 /// Clean up HLS output directory before starting new streaming session
 async fn cleanup_hls_directory(out_dir: &Path) -> Result<(), String> {
     if out_dir.exists() {
@@ -307,29 +427,44 @@ async fn cleanup_hls_directory(out_dir: &Path) -> Result<(), String> {
     Ok(())
 }
 
-async fn process_episode(
-    episode: &crate::models::Episode,
-    _item: &crate::models::PlaylistItem,
-    _played_episodes: Arc<tokio::sync::RwLock<std::collections::HashMap<String, Vec<usize>>>>,
+/// Play a file on the channel. Returns the transport command that
+/// interrupted it, if any.
+async fn encode_file(
+    file_path: &Path,
     out_dir: &Path,
-    subtitle_mode: &SubtitleMode
-) -> Result<(), String> {
-    let file_path = &episode.file_path;
+    subtitle_mode: &SubtitleMode,
+    encode: &mut ChannelEncode<'_>
+) -> Result<Option<ChannelCommand>, String> {
+    let result = run_encode(file_path, out_dir, subtitle_mode, encode).await;
+
+    // From here on commands fail to send, so the API reports them instead of losing them
+    encode.commands.close();
+    *encode.state.channel_commands.write().await = None;
+    result
+}
 
+async fn run_encode(
+    file_path: &Path,
+    out_dir: &Path,
+    subtitle_mode: &SubtitleMode,
+    encode: &mut ChannelEncode<'_>
+) -> Result<Option<ChannelCommand>, String> {
     if !file_path.exists() {
         return Err(format!("File does not exist: {}", file_path.display()));
     }
 
     check_ffmpeg_availability().await?;
 
-    let mut cmd = build_ffmpeg_command(file_path, out_dir, subtitle_mode).await;
-    execute_ffmpeg_streaming(&mut cmd, file_path).await?;
+    let mut cmd = build_ffmpeg_command(file_path, out_dir, subtitle_mode, encode.start).await;
+    if let Some(command) = execute_ffmpeg_streaming(&mut cmd, file_path, Some(encode)).await? {
+        return Ok(Some(command));
+    }
 
     let playlist_path = out_dir.join("index.m3u8");
     match tokio::fs::metadata(&playlist_path).await {
         Ok(metadata) => {
             println!("[playlist] HLS playlist created! ({} bytes)", metadata.len());
-            Ok(())
+            Ok(None)
         }
         Err(_) => Err("HLS playlist file not found after FFmpeg completion".to_string()),
     }
@@ -346,13 +481,250 @@ async fn process_video_file(
 
     check_ffmpeg_availability().await?;
 
-    let mut cmd = build_ffmpeg_command(file, out_dir, subtitle_mode).await;
-    execute_ffmpeg_streaming(&mut cmd, file).await?;
+    let mut cmd = build_ffmpeg_command(file, out_dir, subtitle_mode, 0.0).await;
+    execute_ffmpeg_streaming(&mut cmd, file, None).await?;
 
     println!("[tv] Streaming completed for {}", file.display());
     Ok(())
 }
 
+enum RotationStep {
+    Played,
+    /// Every playlist item has finished
+    Exhausted,
+    /// A `OnceThenStop` item finished and stopped the channel
+    Stopped,
+}
+
+/// Play the next episode the rotation picks. With `resume`, the episode that
+/// was interrupted when the channel last stopped continues where it left off.
+async fn play_next_in_rotation(
+    state: &Arc<AppState>,
+    rotation: &mut Rotation,
+    playlist: &[PlaylistItem],
+    out_dir: &Path,
+    subtitle_mode: &SubtitleMode,
+    resume: bool
+) -> RotationStep {
+    if mem::take(&mut *state.cursors_changed.write().await) {
+        rotation.restart();
+    }
+    if rotation.needs_passes() {
+        let shows = state.shows.read().await;
+        let show_metadata = state.show_metadata.read().await;
+        // Files that failed their last integrity check are skipped
+        let broken = broken_files(&shows, &*state.file_health.read().await);
+        let aired = aired_files(state.cursors.read().await.get(TV_CHANNEL), &shows);
+        rotation.load_passes(&shows, &show_metadata, &broken, &aired);
+    }
+
+    let mode = *state.channel_rotation.read().await;
+    let (index, episode, mut start) = match rotation.take_pending() {
+        Some(pending) => pending,
+        None => {
+            let resumed = if resume { resume_point(state, rotation).await } else { None };
+            let (index, start) = match resumed {
+                Some(point) => point,
+                None => {
+                    let Some(index) = rotation.pick(mode) else {
+                        return RotationStep::Exhausted;
+                    };
+                    (index, 0.0)
+                }
+            };
+            let Some(episode) = rotation.next_episode(index) else {
+                return RotationStep::Played;
+            };
+            (index, episode, start)
+        }
+    };
+    let item = &playlist[index];
+
+    record_airing(state, TV_CHANNEL, &episode, false, start).await;
+
+    *state.current_playing.write().await = Some(episode.file_path.clone());
+    loop {
+        *state.now_playing.write().await = Some(NowPlaying {
+            item: Some(index),
+            order: Some(item.order),
+            started_at: started_at(start),
+            now: ScheduledEpisode::of(&episode),
+            next: upcoming(state, rotation, mode).await,
+        });
+
+        println!("[playlist] Processing {} - {} ({})", episode.show_name, episode.name, item.label());
+
+        let mut encode = ChannelEncode::new(state, Some(&episode), start).await;
+        match encode_file(&episode.file_path, out_dir, subtitle_mode, &mut encode).await {
+            Ok(None) => {
+                println!("[playlist] Episode processed successfully");
+            }
+            Ok(Some(ChannelCommand::Skip)) => {
+                println!("[tv] Skipped {}", episode.name);
+            }
+            Ok(Some(ChannelCommand::Previous)) if rotation.go_back(index, episode.clone()) => {
+                // The interrupted episode plays again after the previous one,
+                // so it is neither finished nor remembered
+                println!("[tv] Going back to the previous episode");
+                return RotationStep::Played;
+            }
+            Ok(Some(ChannelCommand::Previous | ChannelCommand::Restart)) => {
+                println!("[tv] Restarting {}", episode.name);
+                start = 0.0;
+                record_offset(state, TV_CHANNEL, &episode, start).await;
+                continue;
+            }
+            Ok(Some(ChannelCommand::Seek(to))) => {
+                println!("[tv] Seeking {} to {:.0}s", episode.name, to);
+                start = to;
+                record_offset(state, TV_CHANNEL, &episode, start).await;
+                continue;
+            }
+            Ok(Some(ChannelCommand::SwitchPlaylist)) => {
+                // Not finished, so the show's cursor still points at it. If the
+                // playlist ends up unchanged the episode simply continues.
+                println!("[tv] Leaving {} for the new playlist", episode.name);
+                let position = encode.position;
+                rotation.interrupt(index, episode, position);
+                return RotationStep::Played;
+            }
+            Ok(Some(ChannelCommand::PlayQueued)) => {
+                // The episode continues from here once the queue has drained
+                println!("[tv] Interrupting {} for the play-next queue", episode.name);
+                let position = encode.position;
+                record_offset(state, TV_CHANNEL, &episode, position).await;
+                rotation.interrupt(index, episode, position);
+                return RotationStep::Played;
+            }
+            Err(e) => {
+                println!("[playlist] Failed to process episode: {}", e);
+                time::sleep(Duration::from_secs(1)).await;
+            }
+        }
+        break;
+    }
+
+    // Failed and skipped episodes count as aired too, so the show moves on
+    record_airing(state, TV_CHANNEL, &episode, true, 0.0).await;
+    rotation.remember(index, episode);
+
+    if
+        !rotation.has_pending() &&
+        item.play_mode() == PlayMode::OnceThenStop &&
+        rotation.is_item_finished(index)
+    {
+        println!("[tv] Finished {}, stopping the channel", item.label());
+        stop_channel(state).await;
+        return RotationStep::Stopped;
+    }
+    RotationStep::Played
+}
+
+/// Play a file from the play-next queue. Queued files leave the show cursors
+/// alone, so the schedule carries on as if they never aired.
+async fn play_queued(
+    state: &AppState,
+    rotation: &Rotation,
+    queued: &ScheduledEpisode,
+    out_dir: &Path,
+    subtitle_mode: &SubtitleMode
+) {
+    let mode = *state.channel_rotation.read().await;
+    let mut start = 0.0;
+
+    *state.current_playing.write().await = Some(queued.file_path.clone());
+    loop {
+        *state.now_playing.write().await = Some(NowPlaying {
+            item: None,
+            order: None,
+            started_at: started_at(start),
+            now: queued.clone(),
+            next: upcoming(state, rotation, mode).await,
+        });
+
+        println!("[tv] Playing queued file: {}", queued.file_path.display());
+
+        let mut encode = ChannelEncode::new(state, None, start).await;
+        match encode_file(&queued.file_path, out_dir, subtitle_mode, &mut encode).await {
+            Ok(Some(ChannelCommand::Previous | ChannelCommand::Restart)) => {
+                start = 0.0;
+            }
+            Ok(Some(ChannelCommand::Seek(to))) => {
+                start = to;
+            }
+            // Queued files finish before the new playlist starts
+            Ok(Some(ChannelCommand::SwitchPlaylist)) => {
+                start = encode.position;
+            }
+            // Sent just as this file started; nothing new to play
+            Ok(Some(ChannelCommand::PlayQueued)) if state.play_queue.read().await.is_empty() => {
+                start = encode.position;
+            }
+            // Finished, skipped or replaced by a file queued to play now
+            Ok(_) => {
+                break;
+            }
+            Err(e) => {
+                println!("[tv] Failed to play queued file: {}", e);
+                time::sleep(Duration::from_secs(1)).await;
+                break;
+            }
+        }
+    }
+}
+
+/// Queued files, then what the rotation plays after them
+async fn upcoming(state: &AppState, rotation: &Rotation, mode: ChannelRotation) -> Vec<ScheduledEpisode> {
+    let mut next: Vec<ScheduledEpisode> = state.play_queue
+        .read().await
+        .iter()
+        .take(NEXT_UP_LIMIT)
+        .cloned()
+        .collect();
+    next.extend(
+        rotation
+            .preview(mode, NEXT_UP_LIMIT - next.len())
+            .iter()
+            .map(ScheduledEpisode::of)
+    );
+    next
+}
+
+/// Start time, in seconds since the Unix epoch, of a file playing from `offset`
+fn started_at(offset: f64) -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+        .saturating_sub(offset as u64)
+}
+
+/// Item and offset to continue the interrupted episode from, if the playlist
+/// would still play it
+async fn resume_point(state: &AppState, rotation: &mut Rotation) -> Option<(usize, f64)> {
+    let (episode, offset) = interrupted_airing(
+        state.cursors.read().await.get(TV_CHANNEL),
+        &*state.shows.read().await
+    )?;
+    let index = rotation.resume_at(&episode.file_path)?;
+    println!("[tv] Resuming {} at {:.0}s", episode.name, offset);
+    Some((index, offset))
+}
+
+/// Stop the channel from inside its own loop, where `stop_streaming` would
+/// abort the task before the test card is up
+async fn stop_channel(state: &AppState) {
+    state.jobs.write().await.remove("tv");
+    *state.is_playing.write().await = false;
+    *state.channel_commands.write().await = None;
+    *state.current_playing.write().await = None;
+    *state.now_playing.write().await = None;
+
+    if let Err(e) = stream_test_card(&state.hls_root.join("tv")).await {
+        println!("[streaming] Failed to start test card: {}", e);
+    }
+}
+
 pub async fn start_tv_loop_if_needed(state: Arc<AppState>) {
     if state.jobs.read().await.contains_key("tv") {
         return;
@@ -370,27 +742,27 @@ pub async fn start_tv_loop_if_needed(state: Arc<AppState>) {
         return;
     }
 
+    *state.is_playing.write().await = true;
+
     let state_clone = Arc::clone(&state);
 
     let handle = tokio::spawn(async move {
         println!("[tv] Starting streaming loop - checking for content...");
+        let mut rotation = Rotation::new();
+        let mut played_in_cycle = false;
+        // Only the first episode after the channel starts can resume
+        let mut resuming = true;
         loop {
             let tv_files = {
                 let guard = state_clone.tv_files.read().await;
                 guard.clone()
             };
 
-            let shows = {
-                let guard = state_clone.shows.read().await;
-                guard.clone()
-            };
-
             let playlist = {
                 let guard = state_clone.playlist.read().await;
                 guard.clone()
             };
 
-            let played_episodes = Arc::clone(&state_clone.played_episodes);
             let out_dir = state_clone.hls_root.join("tv");
 
             let subtitle_mode = {
@@ -398,83 +770,94 @@ pub async fn start_tv_loop_if_needed(state: Arc<AppState>) {
                 guard.clone()
             };
 
+            // The play-next queue goes before the schedule
+            let queued = state_clone.play_queue.write().await.pop_front();
+            if let Some(queued) = queued {
+                rotation.sync(&playlist);
+                play_queued(
+                    &state_clone,
+                    &rotation,
+                    &queued,
+                    &out_dir,
+                    &subtitle_mode
+                ).await;
+                continue;
+            }
+
             if !playlist.is_empty() {
-                println!("[tv] Using playlist mode with {} items", playlist.len());
-                println!("[tv] Processing playlist items...");
-                println!("[tv] Playlist has {} items to process", playlist.len());
-                for (i, item) in playlist.iter().enumerate() {
-                    println!("[tv] Processing playlist item {}: show='{}'", i, item.show_name);
-                    if let Some(episodes) = shows.get(&item.show_name) {
-                        println!(
-                            "[tv] Found {} episodes for show '{}'",
-                            episodes.len(),
-                            item.show_name
-                        );
-                        let episode_range = item.episode_range;
-                        let episodes_to_play = match episode_range {
-                            Some((start, end)) => {
-                                let start = start.min(episodes.len());
-                                let end = end.min(episodes.len());
-                                &episodes[start..end]
-                            }
-                            None => episodes,
-                        };
-
-                        for episode in episodes_to_play {
-                            if item.repeat_count == 0 {
-                                let played = played_episodes.read().await;
-                                if let Some(played_eps) = played.get(&item.show_name) {
-                                    if played_eps.contains(&episode.id) {
-                                        println!(
-                                            "[playlist] Skipping already played episode: {}",
-                                            episode.name
-                                        );
-                                        continue;
-                                    }
-                                }
-                            }
-
-                            if item.repeat_count == 0 {
-                                played_episodes
-                                    .write().await
-                                    .entry(item.show_name.clone())
-                                    .or_insert_with(Vec::new)
-                                    .push(episode.id);
-                            }
-
-                            println!("[playlist] Processing {} - {}", item.show_name, episode.name);
-
-                            match
-                                process_episode(
-                                    episode,
-                                    item,
-                                    Arc::clone(&played_episodes),
-                                    &out_dir,
-                                    &subtitle_mode
-                                ).await
-                            {
-                                Ok(_) => {
-                                    println!("[playlist] Episode processed successfully");
-                                }
-                                Err(e) => {
-                                    println!("[playlist] Failed to process episode: {}", e);
-                                    time::sleep(Duration::from_secs(1)).await;
-                                }
-                            }
+                rotation.sync(&playlist);
+                let step = play_next_in_rotation(
+                    &state_clone,
+                    &mut rotation,
+                    &playlist,
+                    &out_dir,
+                    &subtitle_mode,
+                    mem::take(&mut resuming)
+                ).await;
+                match step {
+                    RotationStep::Played => {
+                        played_in_cycle = true;
+                        continue;
+                    }
+                    RotationStep::Stopped => {
+                        return;
+                    }
+                    RotationStep::Exhausted => {}
+                }
+
+                let played = mem::take(&mut played_in_cycle);
+                let policy = *state_clone.playlist_end.read().await;
+                match policy {
+                    PlaylistEndPolicy::Restart => {
+                        // `Once` items would otherwise leave nothing to start over with
+                        let forgot = forget_aired(state_clone.cursors.write().await.get_mut(TV_CHANNEL));
+                        if forgot && let Err(e) = save_cursors(&state_clone).await {
+                            eprintln!("[tv] Failed to save cursors: {}", e);
                         }
-                    } else {
-                        eprintln!(
-                            "[tv] No episodes found for show '{}'. Please scan for videos first.",
-                            item.show_name
-                        );
-                        time::sleep(Duration::from_secs(5)).await;
+                        if !played && !forgot {
+                            println!("[tv] Nothing in the playlist can play, stopping the channel");
+                            stop_channel(&state_clone).await;
+                            return;
+                        }
+                        println!("[tv] Playlist finished, starting over");
+                    }
+                    PlaylistEndPolicy::Reset => {
+                        if !played {
+                            println!("[tv] Nothing left to play in the playlist");
+                            time::sleep(Duration::from_secs(5)).await;
+                        }
+                        println!("[tv] Playlist finished, resetting show cursors");
+                        state_clone.cursors.write().await.remove(TV_CHANNEL);
+                        if let Err(e) = save_cursors(&state_clone).await {
+                            eprintln!("[tv] Failed to save cursors: {}", e);
+                        }
+                    }
+                    PlaylistEndPolicy::Stop => {
+                        println!("[tv] Playlist finished, stopping the channel");
+                        stop_channel(&state_clone).await;
+                        return;
                     }
                 }
+                rotation.restart();
             } else if !tv_files.is_empty() {
                 println!("[tv] Using fallback mode with {} files", tv_files.len());
+                *state_clone.now_playing.write().await = None;
+                // Files that failed their last integrity check are skipped
+                let broken = broken_files(
+                    &*state_clone.shows.read().await,
+                    &*state_clone.file_health.read().await
+                );
                 println!("[tv] About to process {} tv_files", tv_files.len());
                 for file in &tv_files {
+                    if !state_clone.play_queue.read().await.is_empty() {
+                        break;
+                    }
+                    if broken.contains(file) {
+                        println!("[tv] Skipping known-bad file: {}", file.display());
+                        continue;
+                    }
                     println!("[tv] Processing file: {}", file.display());
+                    *state_clone.current_playing.write().await = Some(file.clone());
 
                     match process_video_file(file, &out_dir, &subtitle_mode).await {
                         Ok(_) => {
@@ -1,84 +1,596 @@
-use std::{ collections::HashMap, path::{ Path, PathBuf } };
+use std::{
+    cmp::Ordering,
+    collections::{ HashMap, HashSet },
+    path::{ Path, PathBuf },
+    sync::{ Arc, LazyLock },
+    time::{ Instant, UNIX_EPOCH },
+};
 
 use regex::Regex;
+use serde::Serialize;
 use walkdir::WalkDir;
 
-use crate::models::Episode;
+use crate::library::IgnoreRules;
+use crate::metadata::{ read_episode_metadata, ShowMetadataCache };
+use crate::models::{
+    Episode,
+    EpisodeRange,
+    EpisodeRef,
+    LibraryKind,
+    LibraryRoot,
+    MediaInfo,
+    MediaMetadata,
+    Overrides,
+    PlaylistItem,
+    ScanSettings,
+    ShowNaming,
+};
+use crate::parser::{ parse_movie_name, parse_release_name, ParseRules, RuleMatch };
+use crate::scan::ScanProgress;
 
-pub async fn scan_for_videos(folder: &Path) -> Vec<PathBuf> {
+/// A video file found on disk during a scan
+pub struct VideoFile {
+    pub path: PathBuf,
+    pub size: u64,
+    pub modified: Option<u64>,
+    /// From the file's own NFO and thumbnail
+    pub metadata: Option<MediaMetadata>,
+    /// From the enclosing show folder
+    pub show_metadata: Option<MediaMetadata>,
+    /// Reused from the library or probed after the walk
+    pub media_info: Option<MediaInfo>,
+}
+
+#[derive(Serialize, Clone, Default)]
+pub struct MovedFile {
+    pub from: String,
+    pub to: String,
+}
+
+/// Difference between the library before and after a rescan
+#[derive(Serialize, Clone, Default)]
+pub struct ScanDiff {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub changed: Vec<String>,
+    pub moved: Vec<MovedFile>,
+    pub unchanged: usize,
+}
+
+impl ScanDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() &&
+            self.removed.is_empty() &&
+            self.changed.is_empty() &&
+            self.moved.is_empty()
+    }
+
+    pub fn extend(&mut self, other: ScanDiff) {
+        self.added.extend(other.added);
+        self.removed.extend(other.removed);
+        self.changed.extend(other.changed);
+        self.moved.extend(other.moved);
+        self.unchanged += other.unchanged;
+    }
+
+    pub fn summary(&self) -> String {
+        format!(
+            "{} added, {} removed, {} changed, {} moved, {} unchanged",
+            self.added.len(),
+            self.removed.len(),
+            self.changed.len(),
+            self.moved.len(),
+            self.unchanged
+        )
+    }
+}
+
+/// Defaults for `ScanSettings`
+pub const VIDEO_EXTENSIONS: [&str; 12] = [
+    "mp4", "mkv", "avi", "mov", "wmv", "flv", "webm", "m4v", "ts", "m2ts", "mpg", "mpeg",
+];
+pub const AUDIO_EXTENSIONS: [&str; 7] = ["mp3", "flac", "m4a", "aac", "ogg", "opus", "wav"];
+
+/// Walk a library root for media files on a blocking thread, reporting into
+/// `progress`. Returns `Ok(None)` if the scan was cancelled part way through.
+pub async fn scan_for_videos(
+    root: &LibraryRoot,
+    settings: &ScanSettings,
+    progress: Arc<ScanProgress>
+) -> Result<Option<Vec<VideoFile>>, String> {
+    let started = Instant::now();
+    let walk_root = root.clone();
+    let walk_settings = settings.clone();
+    let walk_progress = Arc::clone(&progress);
+
+    let video_files = tokio::task
+        ::spawn_blocking(move || {
+            walk_media_files(&walk_root, &walk_root.path, &walk_settings, &walk_progress)
+        }).await
+        .map_err(|e| format!("Directory walk failed: {}", e))?;
+
+    if let Some(files) = &video_files {
+        println!(
+            "[scan] Walked library '{}' in {} ms: {} files seen, {} media files",
+            root.id,
+            started.elapsed().as_millis(),
+            progress.files_seen(),
+            files.len()
+        );
+    }
+
+    Ok(video_files)
+}
+
+/// Collect media files at or below `start`, which must lie inside `root`
+pub fn walk_media_files(
+    root: &LibraryRoot,
+    start: &Path,
+    settings: &ScanSettings,
+    progress: &ScanProgress
+) -> Option<Vec<VideoFile>> {
     let mut video_files = Vec::new();
-    let video_extensions = ["mp4", "mkv", "avi", "mov", "wmv", "flv", "webm", "m4v"];
+    let ignore = IgnoreRules::new(root, settings);
+    let mut show_metadata = ShowMetadataCache::default();
 
-    println!("[scan] Starting video scan of folder: {}", folder.display());
-    println!("[scan] Looking for extensions: {:?}", video_extensions);
+    if ignore.is_ignored(start, start.is_dir()) {
+        return Some(video_files);
+    }
 
-    for entry in WalkDir::new(folder)
+    // Without following, symlinks are neither files nor directories and get skipped
+    for entry in WalkDir::new(start)
+        .follow_links(settings.follow_symlinks)
         .into_iter()
+        .filter_entry(|e| !ignore.is_ignored(e.path(), e.file_type().is_dir()))
         .filter_map(|e| e.ok()) {
-        if entry.file_type().is_file() {
+        if progress.is_cancelled() {
+            println!("[scan] Scan of {} cancelled", start.display());
+            return None;
+        }
+
+        if entry.file_type().is_dir() {
+            progress.enter_dir(entry.path());
+        } else if entry.file_type().is_file() {
             let path = entry.path();
-            println!("[scan] Found file: {}", path.display());
-
-            if let Some(extension) = path.extension() {
-                if let Some(ext_str) = extension.to_str() {
-                    let ext_lower = ext_str.to_lowercase();
-                    println!("[scan] File extension: {}", ext_lower);
-
-                    if video_extensions.contains(&ext_lower.as_str()) {
-                        println!("[scan] Video file accepted: {}", path.display());
-                        video_files.push(path.to_path_buf());
-                    } else {
-                        println!("[scan] Extension '{}' not in video extensions list", ext_lower);
-                    }
+            progress.file_seen();
+
+            if !has_extension(path, settings.extensions(root.kind)) {
+                continue;
+            }
+
+            let metadata = entry.metadata().ok();
+            if metadata.as_ref().is_some_and(|m| m.len() < settings.min_file_size) {
+                continue;
+            }
+
+            progress.video_accepted();
+            let mut file = video_file(path, metadata);
+            file.metadata = read_episode_metadata(path);
+            file.show_metadata = show_metadata.lookup(&root.path, path);
+            video_files.push(file);
+        }
+    }
+
+    Some(video_files)
+}
+
+fn has_extension(path: &Path, extensions: &[String]) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| extensions.iter().any(|e| e.eq_ignore_ascii_case(ext)))
+        .unwrap_or(false)
+}
+
+pub fn is_audio_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| AUDIO_EXTENSIONS.contains(&ext.to_lowercase().as_str()))
+        .unwrap_or(false)
+}
+
+pub fn video_file(path: &Path, metadata: Option<std::fs::Metadata>) -> VideoFile {
+    VideoFile {
+        path: path.to_path_buf(),
+        size: metadata.as_ref().map(|m| m.len()).unwrap_or(0),
+        modified: metadata
+            .and_then(|m| m.modified().ok())
+            .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+            .map(|d| d.as_secs()),
+        metadata: None,
+        show_metadata: None,
+        media_info: None,
+    }
+}
+
+/// Merge a fresh scan into the existing library.
+///
+/// Only episodes for which `in_scope` holds are compared against `scanned`;
+/// everything else is carried over untouched. Known files keep their episode
+/// entry with only the path-derived fields refreshed, files that vanished are
+/// marked unavailable rather than dropped, and a vanished file that reappears
+/// elsewhere with the same identity is reported as moved.
+pub fn merge_scan(
+    existing: &HashMap<String, Vec<Episode>>,
+    root: &LibraryRoot,
+    scanned: &[VideoFile],
+    rules: &ParseRules,
+    overrides: &Overrides,
+    in_scope: impl Fn(&Episode) -> bool
+) -> (HashMap<String, Vec<Episode>>, ScanDiff) {
+    let mut diff = ScanDiff::default();
+    let mut episodes = Vec::new();
+    let mut previous: HashMap<PathBuf, Episode> = HashMap::new();
+
+    for episode in existing.values().flatten() {
+        if in_scope(episode) {
+            previous.insert(episode.file_path.clone(), episode.clone());
+        } else {
+            episodes.push(episode.clone());
+        }
+    }
+
+    let mut new_files = Vec::new();
+    // Ids stay with their files; new files are numbered after the highest one
+    let mut next_id = existing
+        .values()
+        .flatten()
+        .map(|episode| episode.id + 1)
+        .max()
+        .unwrap_or(0);
+
+    for file in scanned {
+        match previous.remove(&file.path) {
+            Some(mut episode) => {
+                // Entries from older configs carry no fingerprint yet
+                let legacy = episode.file_size == 0 && episode.modified.is_none();
+                let same_file = episode.file_size == file.size && episode.modified == file.modified;
+                if (legacy || same_file) && episode.metadata == file.metadata {
+                    diff.unchanged += 1;
                 } else {
-                    println!("[scan] Could not convert extension to string");
+                    diff.changed.push(file.path.display().to_string());
                 }
-            } else {
-                println!("[scan] No extension found for file: {}", path.display());
+                // Path-derived fields are cheap to recompute and pick up parser improvements
+                apply_parsed(&mut episode, parse_episode_info(&file.path, root, rules).0);
+                apply_file_info(&mut episode, file);
+                apply_overrides(&mut episode, overrides);
+                episodes.push(episode);
             }
+            None => new_files.push(file),
         }
     }
 
-    println!("[scan] Scan complete. Found {} video files", video_files.len());
-    for (i, file) in video_files.iter().enumerate() {
-        println!("[scan] {}: {}", i + 1, file.display());
+    let mut missing: Vec<Option<Episode>> = previous.into_values().map(Some).collect();
+    let missing_by_id: HashMap<String, usize> = missing
+        .iter()
+        .enumerate()
+        .filter_map(|(index, episode)| {
+            let file_id = &episode.as_ref()?.file_id;
+            (!file_id.is_empty()).then(|| (file_id.clone(), index))
+        })
+        .collect();
+
+    new_files.sort_by(|a, b| a.path.cmp(&b.path));
+    for file in new_files {
+        let file_id = file_identity(&file.path, file.size);
+        let previous_entry = missing_by_id
+            .get(&file_id)
+            .and_then(|&index| missing[index].take());
+
+        match previous_entry {
+            Some(mut episode) => {
+                diff.moved.push(MovedFile {
+                    from: episode.file_path.display().to_string(),
+                    to: file.path.display().to_string(),
+                });
+                apply_parsed(&mut episode, parse_episode_info(&file.path, root, rules).0);
+                apply_file_info(&mut episode, file);
+                apply_overrides(&mut episode, overrides);
+                episodes.push(episode);
+            }
+            None => {
+                let mut episode = parse_episode_info(&file.path, root, rules).0;
+                episode.id = next_id;
+                next_id += 1;
+                apply_file_info(&mut episode, file);
+                apply_overrides(&mut episode, overrides);
+                diff.added.push(file.path.display().to_string());
+                episodes.push(episode);
+            }
+        }
     }
 
-    video_files
+    for mut episode in missing.into_iter().flatten() {
+        if episode.available {
+            episode.available = false;
+            diff.removed.push(episode.file_path.display().to_string());
+        }
+        episodes.push(episode);
+    }
+
+    diff.added.sort();
+    diff.removed.sort();
+    diff.changed.sort();
+
+    (organize_shows_and_episodes(episodes), diff)
 }
 
-pub async fn organize_shows_and_episodes(video_files: &[PathBuf]) -> HashMap<String, Vec<Episode>> {
-    let mut shows: HashMap<String, Vec<Episode>> = HashMap::new();
-    let mut episode_id = 0;
+/// All playable files in the library, in show order
+pub fn available_files(shows: &HashMap<String, Vec<Episode>>) -> Vec<PathBuf> {
+    shows
+        .values()
+        .flatten()
+        .filter(|episode| episode.available && !episode.hidden)
+        .map(|episode| episode.file_path.clone())
+        .collect()
+}
 
-    for file_path in video_files {
-        let mut episode = parse_episode_info(file_path);
+/// Episodes a playlist item expands to: one show, or every programme in a
+/// collection and/or genre ordered by year and name
+pub fn playlist_episodes(
+    item: &PlaylistItem,
+    shows: &HashMap<String, Vec<Episode>>,
+    show_metadata: &HashMap<String, MediaMetadata>
+) -> Vec<Episode> {
+    if item.collection.is_none() && item.genre.is_none() {
+        return shows.get(&item.show_name).cloned().unwrap_or_default();
+    }
 
-        episode.id = episode_id;
-        episode_id += 1;
+    let selects = |metadata: &MediaMetadata| {
+        let collection = item.collection.as_ref().is_none_or(|wanted| {
+            metadata.collection.as_ref().is_some_and(|c| c.eq_ignore_ascii_case(wanted))
+        });
+        let genre = item.genre.as_ref().is_none_or(|wanted| {
+            metadata.genres.iter().any(|g| g.eq_ignore_ascii_case(wanted))
+        });
+        collection && genre
+    };
+
+    let mut programmes: Vec<&Vec<Episode>> = shows
+        .iter()
+        .filter(|(name, episodes)| {
+            show_metadata
+                .get(*name)
+                .into_iter()
+                .chain(episodes.iter().filter_map(|episode| episode.metadata.as_ref()))
+                .any(selects)
+        })
+        .map(|(_, episodes)| episodes)
+        .collect();
+    programmes.sort_by_key(|episodes| {
+        episodes.first().map(|episode| (episode.year, episode.show_name.clone()))
+    });
+
+    programmes.into_iter().flatten().cloned().collect()
+}
+
+/// Turn the index slice of an older config into an episode-number
+/// selection: a range when it picks exactly the same files, a list otherwise
+pub fn migrate_episode_range(
+    item: &mut PlaylistItem,
+    shows: &HashMap<String, Vec<Episode>>,
+    show_metadata: &HashMap<String, MediaMetadata>
+) {
+    let Some((start, end)) = item.episode_range.take() else {
+        return;
+    };
+    let episodes = playlist_episodes(item, shows, show_metadata);
+    let last = end.min(episodes.len());
+    let slice = &episodes[start.min(last)..last];
+
+    let spans: Vec<_> = slice.iter().filter_map(EpisodeRef::span).collect();
+    let (Some(from), Some(to)) = (
+        spans.iter().map(|span| *span.start()).min(),
+        spans.iter().map(|span| *span.end()).max(),
+    ) else {
+        println!(
+            "[playlist] Dropped episode range {}..{} of {}: no numbered episodes in it, it now plays everything",
+            start,
+            end,
+            item.label()
+        );
+        return;
+    };
+
+    item.range = Some(EpisodeRange { from, to });
+    let wanted: HashSet<&Path> = slice
+        .iter()
+        .map(|episode| episode.file_path.as_path())
+        .collect();
+    let picked: HashSet<&Path> = episodes
+        .iter()
+        .filter(|episode| item.selects(episode))
+        .map(|episode| episode.file_path.as_path())
+        .collect();
+    if picked != wanted {
+        item.range = None;
+        item.episodes = spans.iter().map(|span| *span.start()).collect();
+        item.episodes.sort();
+        item.episodes.dedup();
+    }
+
+    let selection = match item.range {
+        Some(range) => range.to_string(),
+        None =>
+            item.episodes
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join(", "),
+    };
+    println!("[playlist] Migrated episode range {}..{} of {} to {}", start, end, item.label(), selection);
+}
+
+fn organize_shows_and_episodes(episodes: Vec<Episode>) -> HashMap<String, Vec<Episode>> {
+    let mut next_id = episodes
+        .iter()
+        .map(|episode| episode.id + 1)
+        .max()
+        .unwrap_or(0);
+    let mut shows: HashMap<String, Vec<Episode>> = HashMap::new();
 
-        shows.entry(episode.show_name.clone()).or_insert_with(Vec::new).push(episode);
+    for episode in episodes {
+        shows.entry(episode.show_name.clone()).or_default().push(episode);
     }
 
     for episodes in shows.values_mut() {
-        episodes.sort_by(|a, b| {
+        episodes.sort_by(compare_episodes);
+
+        // Older configs numbered each show from 0, so merging shows through
+        // an override can bring two episodes with the same id together
+        let mut seen = HashSet::new();
+        for episode in episodes.iter_mut() {
+            if !seen.insert(episode.id) {
+                episode.id = next_id;
+                next_id += 1;
+            }
+        }
+    }
+
+    shows
+}
+
+/// Air order within a show: season, then episode number, then file name.
+/// Episodes without a season are treated as season 1; unnumbered ones go last.
+pub fn compare_episodes(a: &Episode, b: &Episode) -> Ordering {
+    a.season
+        .unwrap_or(1)
+        .cmp(&b.season.unwrap_or(1))
+        .then_with(|| {
             match (a.episode_number, b.episode_number) {
                 (Some(a_num), Some(b_num)) => a_num.cmp(&b_num),
-                (Some(_), None) => std::cmp::Ordering::Less,
-                (None, Some(_)) => std::cmp::Ordering::Greater,
-                (None, None) => a.name.cmp(&b.name),
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (None, None) => Ordering::Equal,
             }
-        });
+        })
+        // "12.5" goes right after "12"
+        .then_with(|| a.sub_episode.cmp(&b.sub_episode))
+        .then_with(|| a.name.cmp(&b.name))
+}
 
-        for (index, episode) in episodes.iter_mut().enumerate() {
-            episode.id = index;
-        }
+/// Copy the fields derived from the file path onto an existing episode,
+/// leaving everything else (ids, metadata) untouched
+fn apply_parsed(episode: &mut Episode, parsed: Episode) {
+    episode.file_path = parsed.file_path;
+    episode.name = parsed.name;
+    episode.show_name = parsed.show_name;
+    episode.season = parsed.season;
+    episode.episode_number = parsed.episode_number;
+    episode.episode_end = parsed.episode_end;
+    episode.sub_episode = parsed.sub_episode;
+    episode.special = parsed.special;
+    episode.title = parsed.title;
+    episode.year = parsed.year;
+    episode.library_id = parsed.library_id;
+    episode.hidden = parsed.hidden;
+}
+
+/// Put NFO titles and then manual corrections on top of freshly parsed
+/// fields. Show renames go first so a per-file show name always wins.
+fn apply_overrides(episode: &mut Episode, overrides: &Overrides) {
+    if let Some(title) = episode.metadata.as_ref().and_then(|m| m.title.clone()) {
+        episode.title = Some(title);
     }
 
-    shows
+    if let Some(name) = overrides.shows.get(&episode.show_name) {
+        episode.show_name = name.clone();
+    }
+
+    let Some(fix) = overrides.files.get(&episode.file_id) else {
+        return;
+    };
+    if let Some(show_name) = &fix.show_name {
+        episode.show_name = show_name.clone();
+    }
+    if let Some(season) = fix.season {
+        episode.season = Some(season);
+    }
+    if let Some(episode_number) = fix.episode_number {
+        episode.episode_number = Some(episode_number);
+    }
+    if let Some(title) = &fix.title {
+        episode.title = Some(title.clone());
+    }
+    episode.hidden = fix.hidden;
+}
+
+/// Re-derive every episode's labels from its path and the current overrides
+/// without touching the disk, e.g. after the overrides changed.
+pub fn relabel_library(
+    shows: &HashMap<String, Vec<Episode>>,
+    roots: &[LibraryRoot],
+    rules: &ParseRules,
+    overrides: &Overrides
+) -> HashMap<String, Vec<Episode>> {
+    let episodes = shows
+        .values()
+        .flatten()
+        .cloned()
+        .map(|mut episode| {
+            if let Some(root) = roots.iter().find(|root| root.id == episode.library_id) {
+                let (parsed, _) = parse_episode_info(&episode.file_path, root, rules);
+                apply_parsed(&mut episode, parsed);
+                apply_overrides(&mut episode, overrides);
+            }
+            episode
+        })
+        .collect();
+
+    organize_shows_and_episodes(episodes)
+}
+
+/// Copy what was read from disk: fingerprint, availability, NFO metadata and media info
+fn apply_file_info(episode: &mut Episode, file: &VideoFile) {
+    episode.file_id = file_identity(&file.path, file.size);
+    episode.file_size = file.size;
+    episode.modified = file.modified;
+    episode.available = true;
+    episode.metadata = file.metadata.clone();
+    episode.media_info = file.media_info.clone();
+}
+
+/// Fingerprint an episode saved before fingerprints were kept, from the
+/// file on disk. Returns false if the file can't be read.
+pub fn fill_file_identity(episode: &mut Episode) -> bool {
+    let Ok(metadata) = std::fs::metadata(&episode.file_path) else {
+        return false;
+    };
+    let file = video_file(&episode.file_path, Some(metadata));
+    episode.file_id = file_identity(&file.path, file.size);
+    episode.file_size = file.size;
+    episode.modified = file.modified;
+    true
 }
 
-fn parse_episode_info(file_path: &Path) -> Episode {
+/// Identity of a file that stays the same when it is moved to another folder
+fn file_identity(path: &Path, size: u64) -> String {
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+
+    let hash = fnv1a(name.as_bytes().iter().chain(size.to_le_bytes().iter()));
+    format!("{:016x}", hash)
+}
+
+/// FNV-1a, stable across builds so the value can be persisted
+pub fn fnv1a<'a>(bytes: impl IntoIterator<Item = &'a u8>) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in bytes {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
+/// Derive show, season and episode from a file path. User rules are tried
+/// first; whatever a matching rule does not capture falls back to the
+/// built-in heuristics. Also returns the index of the rule that matched.
+pub fn parse_episode_info(
+    file_path: &Path,
+    root: &LibraryRoot,
+    rules: &ParseRules
+) -> (Episode, Option<usize>) {
     let file_name = file_path
         .file_stem()
         .and_then(|s| s.to_str())
@@ -90,34 +602,227 @@ fn parse_episode_info(file_path: &Path) -> Episode {
         .and_then(|s| s.to_str())
         .unwrap_or("Unknown");
 
-    // Extract episode number from filename patterns like:
-    // "Show Name - 01", "Show Name - 02", "Show Name Episode 5", etc.
-    let episode_number = extract_episode_number(file_name);
+    let relative_path = file_path
+        .strip_prefix(&root.path)
+        .unwrap_or(file_path)
+        .with_extension("")
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/");
+    let rule = rules.find(file_name, &relative_path);
+    let matched = rule.as_ref().map(|m| m.rule);
+    let rule = rule.unwrap_or_default();
+
+    if root.kind == LibraryKind::Movies {
+        return (movie_episode(file_path, root, rule), matched);
+    }
+
+    // "Show/Season 1/ep.mkv" belongs to "Show", not "Season 1"
+    let folder_season = season_from_folder(parent_dir);
+    let show_folder = if folder_season.is_some() {
+        file_path
+            .parent()
+            .and_then(|p| p.parent())
+            .and_then(|p| p.file_name())
+            .and_then(|s| s.to_str())
+            .unwrap_or(parent_dir)
+    } else {
+        parent_dir
+    };
+
+    let release = parse_release_name(file_name);
+
+    let show_name = match root.naming {
+        ShowNaming::ParentFolder => None,
+        ShowNaming::TopFolder => {
+            // Only meaningful when the file sits inside a sub-folder of the root
+            let relative = file_path.strip_prefix(&root.path).ok();
+            relative
+                .filter(|r| r.components().count() > 1)
+                .and_then(|r| r.components().next())
+                .and_then(|c| c.as_os_str().to_str())
+        }
+        ShowNaming::FileName => release.show.as_deref().filter(|s| *s != file_name),
+    }.unwrap_or(show_folder);
+
+    // A season in the file name wins over the folder; specials default to season 0
+    let season = rule.season
+        .or(release.season)
+        .or(folder_season)
+        .or(release.special.map(|_| 0));
+
+    let episode = Episode {
+        id: 0, // Assigned by `merge_scan`
+        name: file_name.to_string(),
+        file_path: file_path.to_path_buf(),
+        show_name: rule.show.unwrap_or_else(|| show_name.to_string()),
+        season,
+        episode_number: rule.episode.or(release.episode),
+        episode_end: release.episode_end,
+        sub_episode: release.sub_episode,
+        special: release.special,
+        title: rule.title.or(release.title),
+        year: None,
+        library_id: root.id.clone(),
+        file_id: String::new(),
+        file_size: 0,
+        modified: None,
+        available: true,
+        hidden: false,
+        metadata: None,
+        media_info: None,
+    };
+
+    (episode, matched)
+}
+
+/// A movie is its own programme named "Title (Year)"; the files of a
+/// multi-part movie become its episodes, numbered by part.
+fn movie_episode(file_path: &Path, root: &LibraryRoot, rule: RuleMatch) -> Episode {
+    let file_name = file_path
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("Unknown");
+
+    let mut movie = parse_movie_name(file_name);
+
+    // "Title (2019)/movie.mkv": the folder carries the name
+    if
+        movie.year.is_none() &&
+        let Some(folder) = file_path.parent().filter(|dir| *dir != root.path) &&
+        let Some(from_folder) = folder
+            .file_name()
+            .and_then(|s| s.to_str())
+            .map(parse_movie_name)
+            .filter(|info| info.year.is_some())
+    {
+        movie.title = from_folder.title;
+        movie.year = from_folder.year;
+    }
+
+    let title = rule.title
+        .or(movie.title)
+        .unwrap_or_else(|| file_name.to_string());
+    let show_name = rule.show.unwrap_or_else(|| {
+        match movie.year {
+            Some(year) => format!("{} ({})", title, year),
+            None => title.clone(),
+        }
+    });
 
     Episode {
-        id: 0, // Will be set when organizing episodes
+        id: 0, // Assigned by `merge_scan`
         name: file_name.to_string(),
         file_path: file_path.to_path_buf(),
-        show_name: parent_dir.to_string(),
-        episode_number,
+        show_name,
+        season: None,
+        episode_number: rule.episode.or(movie.part),
+        episode_end: None,
+        sub_episode: None,
+        special: None,
+        title: Some(title),
+        year: movie.year,
+        library_id: root.id.clone(),
+        file_id: String::new(),
+        file_size: 0,
+        modified: None,
+        available: true,
+        hidden: false,
+        metadata: None,
+        media_info: None,
     }
 }
 
-fn extract_episode_number(filename: &str) -> Option<usize> {
-    // Pattern 1: "Show Name - 01", "Show Name - 02"
-    if let Some(captures) = Regex::new(r"- (\d+)").ok()?.captures(filename) {
-        return captures.get(1)?.as_str().parse().ok();
+static SEASON_FOLDER: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)^(?:season|series|s)[ ._-]*(\d{1,3})$").unwrap()
+});
+static SPECIALS_FOLDER: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)^specials?$").unwrap()
+});
+
+/// "Season 2", "Series 02", "S2"; "Specials" is season 0
+fn season_from_folder(folder: &str) -> Option<usize> {
+    if SPECIALS_FOLDER.is_match(folder) {
+        return Some(0);
     }
+    SEASON_FOLDER.captures(folder)?.get(1)?.as_str().parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::fixtures::{ batch, item, seasons, shows };
 
-    // Pattern 2: "Show Name Episode 5", "Show Name Ep 10"
-    if let Some(captures) = Regex::new(r"(?i)(?:episode|ep)\s+(\d+)").ok()?.captures(filename) {
-        return captures.get(1)?.as_str().parse().ok();
+    fn names<'a>(episodes: impl IntoIterator<Item = &'a Episode>) -> Vec<String> {
+        episodes.into_iter().map(|episode| episode.name.clone()).collect()
     }
 
-    // Pattern 3: "Show Name 01", "Show Name 02" (space before number)
-    if let Some(captures) = Regex::new(r"\s+(\d+)$").ok()?.captures(filename) {
-        return captures.get(1)?.as_str().parse().ok();
+    /// What a migrated item selects
+    fn selection(item: &PlaylistItem) -> String {
+        match item.range {
+            Some(range) => format!("range {}", range),
+            None if item.episodes.is_empty() => "everything".to_string(),
+            None => {
+                let episodes: Vec<String> = item.episodes.iter().map(ToString::to_string).collect();
+                format!("episodes {}", episodes.join(" "))
+            }
+        }
     }
 
-    None
+    #[test]
+    fn migrates_legacy_episode_ranges() {
+        let two_seasons = shows(seasons("A", &[4, 2]));
+        // S1E1-3 in one file, followed by single files of S1E2 to S1E4
+        let mut with_batch = shows(seasons("A", &[4]));
+        with_batch.get_mut("A").unwrap()[0] = batch("A", 1, 1, 3);
+
+        // Library, `episode_range` of the older config, and what it becomes
+        let cases = [
+            (&two_seasons, (0, 6), "range S1E1-S2E2"),
+            (&two_seasons, (1, 3), "range S1E2-S1E3"),
+            (&two_seasons, (3, 5), "range S1E4-S2E1"),
+            (&two_seasons, (5, 6), "range S2E2"),
+            (&two_seasons, (2, 100), "range S1E3-S2E2"),
+            // Nothing left in the slice
+            (&two_seasons, (6, 8), "everything"),
+            // A range would also pick the single files the batch holds
+            (&with_batch, (0, 1), "episodes S1E1"),
+            (&with_batch, (0, 2), "episodes S1E1 S1E2"),
+            (&with_batch, (3, 4), "range S1E4"),
+        ];
+        for (shows, (start, end), expected) in cases {
+            let episodes = &shows["A"];
+            let mut migrated = item("A");
+            migrated.episode_range = Some((start, end));
+            migrate_episode_range(&mut migrated, shows, &HashMap::new());
+
+            let context = format!("{}..{} of {}", start, end, names(episodes).join(", "));
+            assert_eq!(migrated.episode_range, None, "{}", context);
+            assert_eq!(selection(&migrated), expected, "{}", context);
+
+            // Plays the files the old slice did
+            let slice = names(episodes.iter().skip(start).take(end - start));
+            if !slice.is_empty() {
+                let picked = names(episodes.iter().filter(|episode| migrated.selects(episode)));
+                assert_eq!(picked, slice, "{}", context);
+            }
+        }
+    }
+
+    #[test]
+    fn migrated_items_round_trip_through_the_config() {
+        let shows = shows(seasons("A", &[4, 2]));
+        let yaml = "show_name: A\nepisode_range: [3, 5]\nrepeat_count: 0\n";
+        let mut migrated: PlaylistItem = serde_yaml::from_str(yaml).unwrap();
+        migrate_episode_range(&mut migrated, &shows, &HashMap::new());
+
+        let saved = serde_yaml::to_string(&migrated).unwrap();
+        assert!(saved.contains("range: S1E4-S2E1"), "{}", saved);
+        assert!(!saved.contains("episode_range"), "{}", saved);
+
+        let mut reloaded: PlaylistItem = serde_yaml::from_str(&saved).unwrap();
+        migrate_episode_range(&mut reloaded, &shows, &HashMap::new());
+        assert!(reloaded == migrated, "{}", saved);
+    }
 }
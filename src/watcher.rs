@@ -0,0 +1,207 @@
+use std::{ collections::{ HashMap, HashSet }, path::PathBuf, sync::Arc, time::Duration };
+
+use notify::{ EventKind, RecommendedWatcher, RecursiveMode, Watcher };
+use tokio::{ sync::mpsc, time::{ self, Instant } };
+use crate::config::save_config;
+use crate::cursors::update_cursors;
+use crate::library::{ root_for_path, IGNORE_FILE };
+use crate::metadata::update_show_metadata;
+use crate::models::{ AppState, LibraryRoot, ScanSettings };
+use crate::parser::ParseRules;
+use crate::probe::{ probe_missing, reuse_media_info };
+use crate::scan::ScanProgress;
+use crate::video::{ available_files, merge_scan, walk_media_files, ScanDiff, VideoFile };
+
+/// Quiet period after the last event before the library is updated
+const DEBOUNCE: Duration = Duration::from_secs(2);
+/// Upper bound on how long a steady stream of events (e.g. a long copy) can defer an update
+const MAX_DELAY: Duration = Duration::from_secs(30);
+
+/// (Re)start the watcher on all enabled library roots according to the current
+/// settings. Any previous watcher is dropped, which also ends its debounce task.
+pub async fn restart_library_watcher(state: Arc<AppState>) {
+    let mut slot = state.library_watcher.write().await;
+    *slot = None;
+
+    if !*state.watch_library.read().await {
+        return;
+    }
+
+    let roots: Vec<LibraryRoot> = state.libraries
+        .read().await
+        .iter()
+        .filter(|root| root.enabled)
+        .cloned()
+        .collect();
+    if roots.is_empty() {
+        return;
+    }
+
+    let (tx, rx) = mpsc::unbounded_channel();
+    let watcher = notify::recommended_watcher(move |res: notify::Result<notify::Event>| {
+        match res {
+            Ok(event) => {
+                if matches!(event.kind, EventKind::Access(_) | EventKind::Other) {
+                    return;
+                }
+                for path in event.paths {
+                    let _ = tx.send(path);
+                }
+            }
+            Err(e) => eprintln!("[watch] Watcher error: {}", e),
+        }
+    });
+
+    let mut watcher: RecommendedWatcher = match watcher {
+        Ok(watcher) => watcher,
+        Err(e) => {
+            eprintln!("[watch] Failed to create watcher: {}", e);
+            return;
+        }
+    };
+
+    for root in &roots {
+        match watcher.watch(&root.path, RecursiveMode::Recursive) {
+            Ok(()) => println!("[watch] Watching library '{}' at {}", root.id, root.path.display()),
+            Err(e) => eprintln!("[watch] Failed to watch {}: {}", root.path.display(), e),
+        }
+    }
+
+    *slot = Some(watcher);
+
+    tokio::spawn(debounce_events(state.clone(), rx));
+}
+
+async fn debounce_events(state: Arc<AppState>, mut rx: mpsc::UnboundedReceiver<PathBuf>) {
+    while let Some(first) = rx.recv().await {
+        let mut paths = HashSet::from([first]);
+        let deadline = Instant::now() + MAX_DELAY;
+
+        loop {
+            let wait = DEBOUNCE.min(deadline.saturating_duration_since(Instant::now()));
+            match time::timeout(wait, rx.recv()).await {
+                Ok(Some(path)) => {
+                    paths.insert(path);
+                }
+                // Watcher was dropped, nothing left to do
+                Ok(None) => {
+                    return;
+                }
+                Err(_) => {
+                    break;
+                }
+            }
+        }
+
+        apply_changes(&state, paths.into_iter().collect()).await;
+    }
+}
+
+async fn apply_changes(state: &AppState, paths: Vec<PathBuf>) {
+    let roots: Vec<LibraryRoot> = state.libraries
+        .read().await
+        .iter()
+        .filter(|root| root.enabled)
+        .cloned()
+        .collect();
+
+    let mut by_root: HashMap<String, Vec<PathBuf>> = HashMap::new();
+    for path in paths {
+        // An edited NFO, image or ignore file affects the media files in its folder
+        let sidecar =
+            path.file_name().is_some_and(|name| name == IGNORE_FILE) ||
+            path
+                .extension()
+                .and_then(|ext| ext.to_str())
+                .is_some_and(|ext| {
+                    ["nfo", "jpg", "jpeg", "png", "webp"].iter().any(|e| e.eq_ignore_ascii_case(ext))
+                });
+        let path = match path.parent() {
+            Some(dir) if sidecar => dir.to_path_buf(),
+            _ => path,
+        };
+        if let Some(root) = root_for_path(&roots, &path) {
+            by_root.entry(root.id.clone()).or_default().push(path);
+        }
+    }
+
+    let mut total = ScanDiff::default();
+
+    for root in roots {
+        let Some(paths) = by_root.remove(&root.id) else {
+            continue;
+        };
+
+        let lookup_root = root.clone();
+        let lookup = paths.clone();
+        let settings = state.scan_settings.read().await.clone();
+        let walk_settings = settings.clone();
+        let mut video_files = match
+            tokio::task::spawn_blocking(move || {
+                collect_video_files(&lookup_root, &lookup, &walk_settings)
+            }).await
+        {
+            Ok(files) => files,
+            Err(e) => {
+                eprintln!("[watch] Failed to inspect changed paths: {}", e);
+                continue;
+            }
+        };
+
+        reuse_media_info(&mut video_files, &*state.shows.read().await);
+        probe_missing(&mut video_files, &settings, &ScanProgress::default()).await;
+
+        let rules = ParseRules::new(&state.parse_rules.read().await);
+        let overrides = state.overrides.read().await.clone();
+        let mut shows = state.shows.write().await;
+        let (merged, diff) = merge_scan(
+            &shows,
+            &root,
+            &video_files,
+            &rules,
+            &overrides,
+            |episode| {
+                episode.library_id == root.id &&
+                    paths.iter().any(|changed| episode.file_path.starts_with(changed))
+            }
+        );
+        if diff.is_empty() {
+            continue;
+        }
+        *shows = merged;
+        *state.tv_files.write().await = available_files(&shows);
+        update_cursors(state, &shows).await;
+        *state.library_stats.write().await = None;
+        update_show_metadata(&mut *state.show_metadata.write().await, &shows, &video_files);
+        drop(shows);
+
+        println!("[watch] Library '{}' updated: {}", root.id, diff.summary());
+        total.extend(diff);
+    }
+
+    if total.is_empty() {
+        return;
+    }
+
+    if let Err(e) = save_config(state).await {
+        eprintln!("[watch] Failed to save config: {}", e);
+    }
+}
+
+/// Media files currently present at or below each changed path
+fn collect_video_files(
+    root: &LibraryRoot,
+    paths: &[PathBuf],
+    settings: &ScanSettings
+) -> Vec<VideoFile> {
+    let progress = ScanProgress::default();
+    let mut seen = HashSet::new();
+
+    paths
+        .iter()
+        .filter(|path| path.exists())
+        .filter_map(|path| walk_media_files(root, path, settings, &progress))
+        .flatten()
+        .filter(|file| seen.insert(file.path.clone()))
+        .collect()
+}